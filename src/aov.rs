@@ -0,0 +1,151 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::image;
+
+/// a pass of the primary rays written next to the color for compositing.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Aov {
+  /// distance along the ray, the largest distance where nothing is hit.
+  Depth,
+  /// world space surface normal.
+  Normal,
+  /// world space point hit.
+  Position,
+  /// index of the object hit plus one, 0 where nothing is hit.
+  ObjectId,
+  /// color of the object hit, linear.
+  Albedo,
+  /// light the surface reflects from the light itself, before the fog.
+  Direct,
+  /// light the surface reflects from the rest of the scene, before the fog.
+  Reflection
+}
+
+impl Aov {
+  pub const ALL: [Aov; 7] = [Aov::Depth, Aov::Normal, Aov::Position, Aov::ObjectId,
+                             Aov::Albedo, Aov::Direct, Aov::Reflection];
+
+  pub fn get_name(&self) -> &'static str {
+    match self {
+      Aov::Depth => "depth",
+      Aov::Normal => "normal",
+      Aov::Position => "position",
+      Aov::ObjectId => "object-id",
+      Aov::Albedo => "albedo",
+      Aov::Direct => "direct",
+      Aov::Reflection => "reflection"
+    }
+  }
+  pub fn get_channels(&self) -> usize {
+    match self {
+      Aov::Depth | Aov::ObjectId => 1,
+      _ => 3
+    }
+  }
+  fn get_index(&self) -> usize {
+    Aov::ALL.iter().position(|aov| aov == self).unwrap()
+  }
+  /// names joined by commas, or all.
+  pub fn parse_list(s: &str) -> Result<Vec<Aov>, Error> {
+    if s == "all" {
+      return Ok(Aov::ALL.to_vec());
+    }
+    s.split(',').map(|name| name.trim().parse()).collect()
+  }
+}
+
+/// depth, normal, position, object-id, albedo, direct or reflection.
+impl FromStr for Aov {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self, Error> {
+    Aov::ALL.iter().find(|aov| aov.get_name() == s).cloned()
+      .ok_or_else(|| Error::Parse(format!("unknown aov {}", s)))
+  }
+}
+
+/// the file format the passes are saved in.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AovFormat {
+  /// 16 bit png, the passes are mapped to [0, 1] and clamped, see Aovs::get_png_samples.
+  Png,
+  /// 32 bit float pfm, the passes as they are.
+  Pfm
+}
+
+impl AovFormat {
+  pub fn get_extension(&self) -> &'static str {
+    match self {
+      AovFormat::Png => "png",
+      AovFormat::Pfm => "pfm"
+    }
+  }
+}
+
+/// png or pfm.
+impl FromStr for AovFormat {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self, Error> {
+    match s {
+      "png" => Ok(AovFormat::Png),
+      "pfm" => Ok(AovFormat::Pfm),
+      _ => Err(Error::Parse(format!("unknown aov format {}", s)))
+    }
+  }
+}
+
+/// the color and every pass of one frame, row by row from the top.
+pub struct Aovs {
+  width: u32,
+  height: u32,
+  // the largest distance, the depth of the pixels where nothing is hit.
+  max_dist: f32,
+  color: Vec<u32>,
+  // the samples of each pass in the order of Aov::ALL, channels interleaved.
+  passes: Vec<Vec<f32>>
+}
+
+impl Aovs {
+  pub fn new(width: u32, height: u32, max_dist: f32, color: Vec<u32>, passes: Vec<Vec<f32>>) -> Self {
+    Aovs {width: width, height: height, max_dist: max_dist, color: color, passes: passes}
+  }
+
+  pub fn get_width(&self) -> u32 {
+    self.width
+  }
+
+  pub fn get_height(&self) -> u32 {
+    self.height
+  }
+
+  /// packed 0x00RRGGBB pixels, the same as a frame rendered alone.
+  pub fn get_color(&self) -> &[u32] {
+    &self.color
+  }
+
+  /// the samples of the pass, get_channels of them per pixel.
+  pub fn get_pass(&self, aov: Aov) -> &[f32] {
+    &self.passes[aov.get_index()]
+  }
+
+  /// the pass mapped to [0, 1] for a png. depth is divided by the largest
+  /// distance, normals go from [-1, 1] to [0, 1] and object ids are kept whole
+  /// as 16 bit integers. position and light are clamped, pfm keeps them whole.
+  pub fn get_png_samples(&self, aov: Aov) -> Vec<f32> {
+    let pass = self.get_pass(aov);
+    match aov {
+      Aov::Depth => pass.iter().map(|depth| depth/self.max_dist).collect(),
+      Aov::Normal => pass.iter().map(|n| n*0.5 + 0.5).collect(),
+      Aov::ObjectId => pass.iter().map(|id| id/65535.).collect(),
+      _ => pass.to_vec()
+    }
+  }
+
+  pub fn save<P: AsRef<Path>>(&self, aov: Aov, path: P, format: AovFormat) -> Result<(), Error> {
+    match format {
+      AovFormat::Png => image::save_png16(path, &self.get_png_samples(aov), aov.get_channels(), self.width, self.height),
+      AovFormat::Pfm => image::save_pfm(path, self.get_pass(aov), aov.get_channels(), self.width, self.height)
+    }
+  }
+}
@@ -0,0 +1,90 @@
+extern crate raymarcher_ocl_rust;
+
+use std::time::{Duration, Instant};
+
+use raymarcher_ocl_rust::scene::Scene;
+use raymarcher_ocl_rust::scene_file::SceneFile;
+use raymarcher_ocl_rust::scene_objects::sphere::Sphere;
+use raymarcher_ocl_rust::scene_objects::material::Material;
+use raymarcher_ocl_rust::math::Vec3;
+use raymarcher_ocl_rust::error::Error;
+use raymarcher_ocl_rust::render_settings::{RenderSettings, Marching};
+use raymarcher_ocl_rust::renderer::{Renderer, FrameTimings};
+
+use crate::options::Options;
+
+// frames rendered and thrown away first, so kernel compilation and buffer
+// allocation on the first frames don't count.
+const WARMUP_FRAMES: u32 = 5;
+
+// the square on the floor add_spheres spreads the spheres over.
+const SPHERE_GRID_MIN: (f32, f32) = (-20., 5.);
+const SPHERE_GRID_SIZE: f32 = 40.;
+
+// adds count small spheres on a grid on the floor in front of the default camera.
+pub fn add_spheres(scene: &mut Scene, count: u32) {
+  let side = (count as f32).sqrt().ceil().max(1.) as u32;
+  let spacing = SPHERE_GRID_SIZE/side as f32;
+  let radius = spacing*0.3;
+  for i in 0..count {
+    let (x, z) = ((i % side) as f32, (i / side) as f32);
+    let position = Vec3::new(SPHERE_GRID_MIN.0 + (x + 0.5)*spacing, radius, SPHERE_GRID_MIN.1 + (z + 0.5)*spacing);
+    let color = ((i*53 % 256) as u8, (i*97 % 256) as u8, (i*193 % 256) as u8);
+    scene.push(Box::new(Sphere::new(position, radius, color, Material::new(0.))));
+  }
+}
+
+// renders the scene from its camera `frames` times and prints how long the frames
+// took, once with each marching strategy in turn when comparing them.
+pub fn run(renderer: &mut Renderer, scene_file: &SceneFile, options: &Options, frames: u32, compare_marching: bool) -> Result<(), Error> {
+  if !compare_marching {
+    return time_frames(renderer, scene_file, options, &options.settings, frames);
+  }
+
+  let mut settings = options.settings.clone();
+  settings.marching = Marching::PLAIN;
+  loop {
+    time_frames(renderer, scene_file, options, &settings, frames)?;
+    settings.marching = settings.marching.next();
+    if settings.marching == Marching::PLAIN {
+      return Ok(());
+    }
+    println!();
+  }
+}
+
+fn time_frames(renderer: &mut Renderer, scene_file: &SceneFile, options: &Options, settings: &RenderSettings, frames: u32) -> Result<(), Error> {
+  let SceneFile {scene, camera} = scene_file;
+
+  for _ in 0..WARMUP_FRAMES {
+    renderer.render(camera, scene, settings)?;
+  }
+
+  let mut durations = Vec::with_capacity(frames as usize);
+  let mut timings = FrameTimings::default();
+  for _ in 0..frames {
+    let start = Instant::now();
+    renderer.render(camera, scene, settings)?;
+    durations.push(start.elapsed());
+    timings += renderer.get_timings();
+  }
+
+  let total: Duration = durations.iter().sum();
+  let average = total.as_secs_f32()*1000./(frames.max(1) as f32);
+  let min = durations.iter().min().cloned().unwrap_or_default().as_secs_f32()*1000.;
+  let max = durations.iter().max().cloned().unwrap_or_default().as_secs_f32()*1000.;
+  println!("{} frames at {}x{} of {} objects, {}, {} marching at {} quality.", frames, options.width, options.height, scene.len(),
+           if settings.bvh {"with the bounding volume hierarchy"} else {"without the bounding volume hierarchy"},
+           settings.marching, settings.quality.get_name());
+  println!("average {:.2}ms ({:.1} fps), min {:.2}ms, max {:.2}ms.", average, 1000./average, min, max);
+  let per_frame = |duration: Duration| duration.as_secs_f32()*1000./(frames.max(1) as f32);
+  println!("upload {:.2}ms, kernel {:.2}ms, readback {:.2}ms per frame.", per_frame(timings.upload), per_frame(timings.kernel), per_frame(timings.readback));
+
+  // only the whitted frames count their steps.
+  if !settings.is_path_traced() && !settings.progressive && settings.stereo.is_none() {
+    let iterations = renderer.read_iterations()?;
+    let steps: u64 = iterations.iter().map(|&steps| steps as u64).sum();
+    println!("{:.1} march steps per pixel for the primary rays.", steps as f32/iterations.len().max(1) as f32);
+  }
+  Ok(())
+}
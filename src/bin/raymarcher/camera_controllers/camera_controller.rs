@@ -0,0 +1,16 @@
+extern crate minifb;
+
+use minifb::Window;
+
+use raymarcher_ocl_rust::camera::Camera;
+use raymarcher_ocl_rust::scene::Scene;
+use raymarcher_ocl_rust::render_settings::Quality;
+
+pub trait CameraController {
+  // called when the viewer switches to this controller, to pick up the camera where the last one left it.
+  // quality has the limits the scene is marched with when picking points.
+  fn activate(&mut self, camera: &Camera, scene: &Scene, quality: &Quality);
+  // moves the camera from this frame's window input.
+  fn update(&mut self, window: &Window, camera: &mut Camera, scene: &Scene, quality: &Quality);
+  fn get_name(&self) -> &str;
+}
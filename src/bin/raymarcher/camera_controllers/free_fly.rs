@@ -0,0 +1,97 @@
+extern crate minifb;
+
+use minifb::{Key, Window, MouseMode, MouseButton};
+
+use super::camera_controller::CameraController;
+use raymarcher_ocl_rust::camera::Camera;
+use raymarcher_ocl_rust::scene::Scene;
+use raymarcher_ocl_rust::render_settings::Quality;
+
+const MOVE_SPEED: f32 = 0.5;
+const ROLL_SPEED: f32 = 0.03;
+const LOOK_SPEED: f32 = 0.01;
+
+// WASD to move, Q and E to move up and down, Z and C to roll and left drag to look around.
+pub struct FreeFlyController {
+  last_mouse: (f32, f32)
+}
+
+impl FreeFlyController {
+  pub fn new() -> Self {
+    FreeFlyController {last_mouse: (0., 0.)}
+  }
+}
+
+impl CameraController for FreeFlyController {
+  fn activate(&mut self, _camera: &Camera, _scene: &Scene, _quality: &Quality) {}
+
+  fn update(&mut self, window: &Window, camera: &mut Camera, _scene: &Scene, _quality: &Quality) {
+    let mut move_forward = false;
+    let mut move_left = false;
+    let mut move_right = false;
+    let mut move_backward = false;
+    let mut move_up = false;
+    let mut move_down = false;
+    let mut roll_left = false;
+    let mut roll_right = false;
+
+    // Handle Keyboard Input
+    window.get_keys().map(|keys| {
+      for t in keys {
+          match t {
+            Key::W => move_forward = true,
+            Key::A => move_left = true,
+            Key::D => move_right = true,
+            Key::S => move_backward = true,
+            Key::Q => move_up = true,
+            Key::E => move_down = true,
+            Key::Z => roll_left = true,
+            Key::C => roll_right = true,
+            _ => {}
+          }
+      }
+    });
+
+    //Handle Mouse Input
+    if window.get_mouse_down(MouseButton::Left) {
+      let (width, height) = window.get_size();
+      let last_mouse = &mut self.last_mouse;
+      window.get_mouse_pos(MouseMode::Clamp).map(|mouse| {
+        let mouse_x = mouse.0 - (width as f32)/2.;
+        let mouse_y = (height as f32)/2. - mouse.1;
+        camera.yaw(LOOK_SPEED*(mouse_x - last_mouse.0));
+        camera.pitch(LOOK_SPEED*(last_mouse.1 - mouse_y));
+        *last_mouse = (mouse_x, mouse_y);
+      });
+    }
+
+    if move_forward {
+      camera.move_forward(MOVE_SPEED);
+    }
+    if move_backward {
+      camera.move_forward(-MOVE_SPEED);
+    }
+    if move_right {
+      camera.move_right(MOVE_SPEED);
+    }
+    if move_left {
+      camera.move_right(-MOVE_SPEED);
+    }
+    if move_up {
+      camera.move_up(MOVE_SPEED);
+    }
+    if move_down {
+      camera.move_up(-MOVE_SPEED);
+    }
+    if roll_left {
+      camera.roll(ROLL_SPEED);
+    }
+    if roll_right {
+      camera.roll(-ROLL_SPEED);
+    }
+  }
+
+  fn get_name(&self) -> &str {
+    "free fly"
+  }
+}
@@ -0,0 +1,3 @@
+pub mod camera_controller;
+pub mod free_fly;
+pub mod orbit;
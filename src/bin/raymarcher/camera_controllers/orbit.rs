@@ -0,0 +1,121 @@
+extern crate minifb;
+
+use minifb::{Window, MouseMode, MouseButton};
+
+use std::f32::consts::FRAC_PI_2;
+use std::time::{Duration, Instant};
+
+use super::camera_controller::CameraController;
+use raymarcher_ocl_rust::camera::Camera;
+use raymarcher_ocl_rust::scene::Scene;
+use raymarcher_ocl_rust::render_settings::Quality;
+use raymarcher_ocl_rust::math::Vec3;
+
+const ORBIT_SPEED: f32 = 0.01;
+// fraction of the distance to the target panned per pixel of mouse movement.
+const PAN_SPEED: f32 = 0.002;
+const ZOOM_FACTOR: f32 = 0.9;
+const MIN_DISTANCE: f32 = 0.1;
+// orbit distance used when nothing is in front of the camera on activation.
+const DEFAULT_DISTANCE: f32 = 10.;
+// keeps the camera from flipping over the poles of the target.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(300);
+
+// left drag to rotate around the target, scroll to zoom, middle drag to pan
+// and double click on an object to make it the target.
+pub struct OrbitController {
+  target: Vec3,
+  yaw: f32,
+  pitch: f32,
+  distance: f32,
+  last_mouse: Option<(f32, f32)>,
+  left_was_down: bool,
+  last_click: Option<Instant>
+}
+
+impl OrbitController {
+  pub fn new() -> Self {
+    OrbitController {target: Vec3::ZERO,
+                    yaw: 0.,
+                    pitch: 0.,
+                    distance: DEFAULT_DISTANCE,
+                    last_mouse: None,
+                    left_was_down: false,
+                    last_click: None}
+  }
+
+  // orbits around point from where the camera is now.
+  fn retarget(&mut self, camera: &Camera, point: Vec3) {
+    let to_point = point - camera.get_position();
+    let dir = to_point.normalize();
+    self.target = point;
+    self.distance = to_point.length().max(MIN_DISTANCE);
+    self.yaw = dir.x.atan2(dir.z);
+    self.pitch = (-dir.y).max(-1.).min(1.).asin().max(-MAX_PITCH).min(MAX_PITCH);
+  }
+}
+
+impl CameraController for OrbitController {
+  fn activate(&mut self, camera: &Camera, scene: &Scene, quality: &Quality) {
+    let position = camera.get_position();
+    let target = match scene.raycast(position, camera.get_look_dir(), quality) {
+      Some((point, _)) => point,
+      None => position + camera.get_look_dir()*DEFAULT_DISTANCE
+    };
+    self.retarget(camera, target);
+    self.last_mouse = None;
+    self.last_click = None;
+  }
+
+  fn update(&mut self, window: &Window, camera: &mut Camera, scene: &Scene, quality: &Quality) {
+    let left_down = window.get_mouse_down(MouseButton::Left);
+    let middle_down = window.get_mouse_down(MouseButton::Middle);
+    let mouse = window.get_mouse_pos(MouseMode::Clamp);
+
+    if let (Some(mouse), Some(last_mouse)) = (mouse, self.last_mouse) {
+      let delta = (mouse.0 - last_mouse.0, mouse.1 - last_mouse.1);
+      if left_down {
+        self.yaw += ORBIT_SPEED*delta.0;
+        self.pitch = (self.pitch + ORBIT_SPEED*delta.1).max(-MAX_PITCH).min(MAX_PITCH);
+      }
+      if middle_down {
+        let pan = camera.get_right_dir()*-delta.0 + camera.get_up_dir()*delta.1;
+        self.target += pan*(PAN_SPEED*self.distance);
+      }
+    }
+    self.last_mouse = if left_down || middle_down {mouse} else {None};
+
+    if left_down && !self.left_was_down {
+      let now = Instant::now();
+      let double_click = self.last_click.map_or(false, |last_click| now.duration_since(last_click) < DOUBLE_CLICK_TIME);
+      if double_click {
+        let (width, height) = window.get_size();
+        let hit = mouse
+          .and_then(|mouse| camera.get_ray(mouse, width as u32, height as u32))
+          .and_then(|(start, direction)| scene.raycast(start, direction, quality));
+        if let Some((point, _)) = hit {
+          self.retarget(camera, point);
+        }
+        self.last_click = None;
+      }
+      else {
+        self.last_click = Some(now);
+      }
+    }
+    self.left_was_down = left_down;
+
+    if let Some((_, scroll)) = window.get_scroll_wheel() {
+      if scroll != 0. {
+        self.distance = (self.distance*ZOOM_FACTOR.powf(scroll.signum())).max(MIN_DISTANCE);
+      }
+    }
+
+    camera.set_yaw_pitch_roll(self.yaw, self.pitch, 0.);
+    camera.set_position(self.target - camera.get_look_dir()*self.distance);
+  }
+
+  fn get_name(&self) -> &str {
+    "orbit"
+  }
+}
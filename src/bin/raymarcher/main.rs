@@ -0,0 +1,142 @@
+extern crate clap;
+extern crate raymarcher_ocl_rust;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+
+use raymarcher_ocl_rust::scene_file::SceneFile;
+use raymarcher_ocl_rust::camera_path::CameraPath;
+use raymarcher_ocl_rust::aov::{Aov, AovFormat};
+use raymarcher_ocl_rust::error::Error;
+use raymarcher_ocl_rust::devices;
+use raymarcher_ocl_rust::config::Config;
+use raymarcher_ocl_rust::image;
+use raymarcher_ocl_rust::renderer::Renderer;
+
+mod camera_controllers;
+mod options;
+mod view;
+mod render;
+mod bench;
+
+use options::Options;
+
+fn main() {
+  let scene_arg = Arg::with_name("scene").required(true).help("Scene file to render");
+
+  let matches = App::new("raymarcher")
+    .about("Sphere traces signed distance field scenes with OpenCL")
+    .setting(AppSettings::SubcommandRequiredElseHelp)
+    .subcommand(SubCommand::with_name("view")
+      .about("Opens a window to fly around a scene")
+      .arg(scene_arg.clone())
+      .args(&options::args())
+      .arg(Arg::with_name("fps").long("fps").takes_value(true).default_value("60").help("Frame rate cap, 0 for none"))
+      .arg(Arg::with_name("stats-interval").long("stats-interval").takes_value(true).default_value("300")
+        .help("Frames between printed frame times, 0 for none")))
+    .subcommand(SubCommand::with_name("render")
+      .about("Renders a scene from its camera to a png")
+      .arg(scene_arg.clone())
+      .args(&options::args())
+      .arg(Arg::with_name("output").short("o").long("output").takes_value(true).default_value("out.png")
+        .help("Output png, or directory of frames with --camera-path"))
+      .arg(Arg::with_name("samples").long("samples").takes_value(true).default_value("1")
+        .help("Frames accumulated with --progressive or the path integrator"))
+      .arg(Arg::with_name("camera-path").long("camera-path").takes_value(true)
+        .help("Renders every frame of a camera path file instead"))
+      .arg(Arg::with_name("aovs").long("aovs").takes_value(true).conflicts_with("camera-path")
+        .help("Also saves these passes of one whitted sample per pixel next to the output: all, or any of depth, normal, position, object-id, albedo, direct and reflection joined by commas"))
+      .arg(Arg::with_name("aov-format").long("aov-format").takes_value(true).possible_values(&["png", "pfm"]).default_value("png")
+        .help("16 bit png with the passes mapped to [0, 1], or 32 bit float pfm")))
+    .subcommand(SubCommand::with_name("devices")
+      .about("Lists the OpenCL devices and marks the one that would be used")
+      .args(&options::device_args())
+      .arg(Arg::with_name("save").long("save").help("Saves --platform and --device as the default selection")))
+    .subcommand(SubCommand::with_name("bench")
+      .about("Times rendering a scene from its camera")
+      .arg(scene_arg)
+      .args(&options::args())
+      .arg(Arg::with_name("frames").long("frames").takes_value(true).default_value("100").help("Frames to time"))
+      .arg(Arg::with_name("objects").long("objects").takes_value(true)
+        .help("Adds a grid of this many small spheres to the scene, for timing large scenes"))
+      .arg(Arg::with_name("compare-marching").long("compare-marching")
+        .help("Times every marching strategy in turn instead of the --marching one")))
+    .get_matches();
+
+  let result = match matches.subcommand() {
+    ("view", Some(matches)) => view(matches),
+    ("render", Some(matches)) => render(matches),
+    ("devices", Some(matches)) => list_devices(matches),
+    ("bench", Some(matches)) => bench(matches),
+    _ => Ok(())
+  };
+
+  if let Err(e) = result {
+    eprintln!("{}", e);
+    std::process::exit(1);
+  }
+}
+
+fn create_renderer(options: &Options) -> Result<Renderer, Error> {
+  let (platform, device) = devices::select(options.platform.as_ref(), options.device.as_ref())?;
+  Ok(Renderer::new(platform, device, options.width, options.height)?)
+}
+
+fn view(matches: &ArgMatches) -> Result<(), Error> {
+  let options = Options::from_matches(matches)?;
+  let scene_file = SceneFile::load(matches.value_of("scene").unwrap())?;
+  let mut renderer = create_renderer(&options)?;
+  let fps = options::value(matches, "fps")?.unwrap_or(60);
+  let stats_interval = options::value(matches, "stats-interval")?.unwrap_or(300);
+  view::run(&mut renderer, scene_file, &options, fps, stats_interval)
+}
+
+fn render(matches: &ArgMatches) -> Result<(), Error> {
+  let options = Options::from_matches(matches)?;
+  let SceneFile {scene, camera} = SceneFile::load(matches.value_of("scene").unwrap())?;
+  let mut renderer = create_renderer(&options)?;
+  let output = matches.value_of("output").unwrap();
+
+  if let Some(camera_path) = matches.value_of("camera-path") {
+    let camera_path = CameraPath::load(camera_path)?;
+    return render::render_sequence(&mut renderer, &camera, &scene, &options.settings, &camera_path, output);
+  }
+
+  if let Some(aovs) = matches.value_of("aovs") {
+    let aovs = Aov::parse_list(aovs)?;
+    let format = options::value(matches, "aov-format")?.unwrap_or(AovFormat::Png);
+    return render::render_aovs(&mut renderer, &camera, &scene, &options.settings, &aovs, format, output);
+  }
+
+  let samples = options::value(matches, "samples")?.unwrap_or(1);
+  let pixels = render::render_image(&mut renderer, &camera, &scene, &options.settings, samples)?;
+  image::save_png(output, &pixels, options.width, options.height)
+}
+
+fn list_devices(matches: &ArgMatches) -> Result<(), Error> {
+  let (platform, device) = options::device_selection(matches)?;
+  let selected = devices::select(platform.as_ref(), device.as_ref()).ok().map(|(_, device)| device);
+
+  for info in devices::list()? {
+    let marker = if selected == Some(info.device) {"* "} else {""};
+    println!("{}{}", marker, info);
+  }
+
+  if matches.is_present("save") {
+    let path = Config::default_path().ok_or_else(|| Error::Parse(String::from("no home directory to save the config in")))?;
+    devices::select(platform.as_ref(), device.as_ref())?;
+    Config {platform: platform, device: device}.save(&path)?;
+    println!("saved the device selection to {}.", path.display());
+  }
+  Ok(())
+}
+
+fn bench(matches: &ArgMatches) -> Result<(), Error> {
+  let options = Options::from_matches(matches)?;
+  let mut scene_file = SceneFile::load(matches.value_of("scene").unwrap())?;
+  if let Some(objects) = options::value(matches, "objects")? {
+    bench::add_spheres(&mut scene_file.scene, objects);
+  }
+  let mut renderer = create_renderer(&options)?;
+  let frames = options::value(matches, "frames")?.unwrap_or(100);
+  bench::run(&mut renderer, &scene_file, &options, frames, matches.is_present("compare-marching"))
+}
@@ -0,0 +1,161 @@
+extern crate clap;
+extern crate raymarcher_ocl_rust;
+
+use std::str::FromStr;
+
+use clap::{Arg, ArgMatches};
+
+use raymarcher_ocl_rust::render_settings::RenderSettings;
+use raymarcher_ocl_rust::devices::Selector;
+use raymarcher_ocl_rust::config::Config;
+use raymarcher_ocl_rust::error::Error;
+
+// the options shared by every subcommand that renders.
+pub struct Options {
+  pub width: u32,
+  pub height: u32,
+  pub platform: Option<Selector>,
+  pub device: Option<Selector>,
+  pub settings: RenderSettings
+}
+
+// the platform and device selection, also taken by the devices subcommand.
+pub fn device_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+  vec![
+    Arg::with_name("platform").long("platform").takes_value(true)
+      .help("OpenCL platform index, name substring or device type, defaults to the saved config"),
+    Arg::with_name("device").long("device").takes_value(true)
+      .help("OpenCL device index, name substring or type (gpu, cpu, accelerator), defaults to the saved config")
+  ]
+}
+
+pub fn args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+  let mut args = device_args();
+  args.extend(vec![
+    Arg::with_name("width").long("width").takes_value(true).default_value("640").help("Image width in pixels"),
+    Arg::with_name("height").long("height").takes_value(true).default_value("320").help("Image height in pixels"),
+    Arg::with_name("integrator").long("integrator").takes_value(true).possible_values(&["whitted", "path"]).help("Shading model"),
+    Arg::with_name("anti-aliasing").long("anti-aliasing").takes_value(true)
+      .help("none, grid:<n>, rotated-grid, stratified:<n> or adaptive:<n>:<threshold>"),
+    Arg::with_name("progressive").long("progressive").help("Accumulate jittered samples while nothing changes"),
+    Arg::with_name("god-rays").long("god-rays").takes_value(true).help("Light samples along each primary ray for god rays, 0 disables them"),
+    Arg::with_name("max-bounces").long("max-bounces").takes_value(true).help("Longest path the path tracer follows"),
+    Arg::with_name("tone-mapping").long("tone-mapping").takes_value(true).possible_values(&["none", "reinhard", "aces", "filmic"]),
+    Arg::with_name("exposure").long("exposure").takes_value(true).help("Linear multiplier applied before tone mapping"),
+    Arg::with_name("no-bvh").long("no-bvh").help("Evaluates every object at every step instead of only the nearby ones"),
+    Arg::with_name("tile-culling").long("tile-culling").takes_value(true)
+      .help("Culls the objects of the primary rays per screen tile of this many pixels"),
+    Arg::with_name("marching").long("marching").takes_value(true)
+      .help("plain, or any of relaxed[:<factor>], cone[:<size>] and footprint joined by +"),
+    Arg::with_name("quality").long("quality").takes_value(true).possible_values(&["draft", "interactive", "final"])
+      .help("Hit distance, step and reflection limits preset"),
+    Arg::with_name("max-iterations").long("max-iterations").takes_value(true)
+      .help("Steps a ray takes before it gives up, overrides the --quality preset"),
+    Arg::with_name("max-dist").long("max-dist").takes_value(true)
+      .help("Distance from every object a ray counts as having left the scene at, overrides the --quality preset"),
+    Arg::with_name("smallest-dist").long("smallest-dist").takes_value(true)
+      .help("Distance to an object a ray counts as hitting it at, overrides the --quality preset"),
+    Arg::with_name("max-reflection-depth").long("max-reflection-depth").takes_value(true)
+      .help("Most mirror reflections a whitted ray follows, overrides the --quality preset"),
+    Arg::with_name("stereo").long("stereo").takes_value(true)
+      .help("side-by-side, top-bottom or anaglyph, optionally followed by :<interocular distance>:<convergence distance>"),
+    Arg::with_name("debug-view").long("debug-view").takes_value(true)
+      .possible_values(&["shaded", "normals", "depth", "iterations", "object-id", "shadow"])
+      .help("Shows the normals, depth, march steps, objects or shadows instead of the shaded color")
+  ]);
+  args
+}
+
+// the selection from the command line, or the saved one for whichever is not given.
+pub fn device_selection(matches: &ArgMatches) -> Result<(Option<Selector>, Option<Selector>), Error> {
+  let config = match Config::default_path() {
+    Some(path) => Config::load(path)?,
+    None => Config::default()
+  };
+  let platform: Option<Selector> = value(matches, "platform")?;
+  let device: Option<Selector> = value(matches, "device")?;
+  if platform.is_none() && device.is_none() {
+    Ok((config.platform, config.device))
+  } else {
+    Ok((platform, device))
+  }
+}
+
+// parses the value of an argument if it was given.
+pub fn value<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<Option<T>, Error> {
+  match matches.value_of(name) {
+    Some(value) => value.parse::<T>().map(Some)
+      .map_err(|_| Error::Parse(format!("invalid value {} for --{}", value, name))),
+    None => Ok(None)
+  }
+}
+
+// parses the value of an argument if it was given, which has to be above 0.
+fn positive(matches: &ArgMatches, name: &str) -> Result<Option<f32>, Error> {
+  match value::<f32>(matches, name)? {
+    Some(value) if value <= 0. => Err(Error::Parse(format!("--{} has to be above 0", name))),
+    value => Ok(value)
+  }
+}
+
+impl Options {
+  pub fn from_matches(matches: &ArgMatches) -> Result<Self, Error> {
+    let mut settings = RenderSettings::new();
+    if let Some(integrator) = value(matches, "integrator")? {
+      settings.integrator = integrator;
+    }
+    if let Some(anti_aliasing) = value(matches, "anti-aliasing")? {
+      settings.anti_aliasing = anti_aliasing;
+    }
+    settings.progressive = matches.is_present("progressive");
+    settings.bvh = !matches.is_present("no-bvh");
+    settings.tile_culling = value(matches, "tile-culling")?;
+    if let Some(marching) = value(matches, "marching")? {
+      settings.marching = marching;
+    }
+    if let Some(quality) = value(matches, "quality")? {
+      settings.quality = quality;
+    }
+    if let Some(max_iterations) = value(matches, "max-iterations")? {
+      settings.quality.max_iterations = max_iterations;
+    }
+    if let Some(max_dist) = positive(matches, "max-dist")? {
+      settings.quality.max_dist = max_dist;
+    }
+    if let Some(smallest_dist) = positive(matches, "smallest-dist")? {
+      settings.quality.smallest_dist = smallest_dist;
+    }
+    if let Some(max_reflection_depth) = value(matches, "max-reflection-depth")? {
+      settings.quality.max_reflection_depth = max_reflection_depth;
+    }
+    settings.stereo = value(matches, "stereo")?;
+    if let Some(debug_view) = value(matches, "debug-view")? {
+      settings.debug_view = debug_view;
+    }
+    if let Some(god_ray_samples) = value(matches, "god-rays")? {
+      settings.god_ray_samples = god_ray_samples;
+    }
+    if let Some(max_bounces) = value(matches, "max-bounces")? {
+      settings.max_bounces = max_bounces;
+    }
+    if let Some(tone_mapping) = value(matches, "tone-mapping")? {
+      settings.tone_mapping = tone_mapping;
+    }
+    if let Some(exposure) = value(matches, "exposure")? {
+      settings.exposure = exposure;
+    }
+
+    let width: u32 = value(matches, "width")?.unwrap_or(640);
+    let height: u32 = value(matches, "height")?.unwrap_or(320);
+    if width < 1 || height < 1 {
+      return Err(Error::Parse(format!("the image has to be at least 1 pixel wide and tall, not {}x{}", width, height)));
+    }
+
+    let (platform, device) = device_selection(matches)?;
+    Ok(Options {width: width,
+                height: height,
+                platform: platform,
+                device: device,
+                settings: settings})
+  }
+}
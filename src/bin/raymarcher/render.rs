@@ -0,0 +1,71 @@
+extern crate raymarcher_ocl_rust;
+
+use std::path::Path;
+
+use raymarcher_ocl_rust::camera::Camera;
+use raymarcher_ocl_rust::scene::Scene;
+use raymarcher_ocl_rust::render_settings::RenderSettings;
+use raymarcher_ocl_rust::camera_path::CameraPath;
+use raymarcher_ocl_rust::aov::{Aov, AovFormat};
+use raymarcher_ocl_rust::error::Error;
+use raymarcher_ocl_rust::image;
+use raymarcher_ocl_rust::renderer::Renderer;
+
+const SEQUENCE_FRAME_RATE: f32 = 30.;
+
+// renders one image, accumulating `samples` frames when the settings are progressive
+// or path traced. stereo images accumulate each eye in turn and combine them.
+pub fn render_image(renderer: &mut Renderer, camera: &Camera, scene: &Scene, settings: &RenderSettings, samples: u32) -> Result<Vec<u32>, Error> {
+  if let Some(stereo) = settings.stereo {
+    let mut eye_settings = settings.clone();
+    eye_settings.stereo = None;
+    let (left_camera, right_camera) = stereo.get_eyes(camera);
+    let left = render_image(renderer, &left_camera, scene, &eye_settings, samples)?;
+    let right = render_image(renderer, &right_camera, scene, &eye_settings, samples)?;
+    return Ok(stereo.composite(&left, &right, renderer.get_width() as usize, renderer.get_height() as usize));
+  }
+
+  let frames = if settings.progressive || settings.is_path_traced() {samples.max(1)} else {1};
+  for _ in 1..frames {
+    renderer.render(camera, scene, settings)?;
+  }
+  Ok(renderer.render(camera, scene, settings)?.to_vec())
+}
+
+// renders the color and the passes in one go, saving the color to output and each
+// pass next to it as <output stem>.<pass>.<extension of the format>.
+pub fn render_aovs<P: AsRef<Path>>(renderer: &mut Renderer, camera: &Camera, scene: &Scene, settings: &RenderSettings, aovs: &[Aov], format: AovFormat, output: P) -> Result<(), Error> {
+  let output = output.as_ref();
+  let rendered = renderer.render_aovs(camera, scene, settings)?;
+  image::save_png(output, rendered.get_color(), rendered.get_width(), rendered.get_height())?;
+
+  let stem = output.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+  for aov in aovs {
+    let path = output.with_file_name(format!("{}.{}.{}", stem, aov.get_name(), format.get_extension()));
+    rendered.save(*aov, path, format)?;
+  }
+  println!("saved {} passes next to {}.", aovs.len(), output.display());
+  Ok(())
+}
+
+// renders every frame of the camera path to a numbered png in directory.
+pub fn render_sequence<P: AsRef<Path>>(renderer: &mut Renderer, camera: &Camera, scene: &Scene, settings: &RenderSettings, camera_path: &CameraPath, directory: P) -> Result<(), Error> {
+  let directory = directory.as_ref();
+  std::fs::create_dir_all(directory)?;
+
+  let mut camera = camera.clone();
+  let frame_count = (camera_path.get_duration()*SEQUENCE_FRAME_RATE).ceil() as u32 + 1;
+  for frame in 0..frame_count {
+    camera_path.apply(&mut camera, camera_path.get_start_time() + frame as f32/SEQUENCE_FRAME_RATE);
+
+    let (width, height) = (renderer.get_width(), renderer.get_height());
+    let pixels = match settings.stereo {
+      Some(stereo) => renderer.render_stereo_frame(&camera, scene, settings, &stereo)?,
+      None => renderer.render_frame(&camera, scene, settings)?
+    };
+
+    image::save_png(directory.join(format!("frame_{:05}.png", frame)), pixels, width, height)?;
+  }
+  println!("rendered {} frames to {}.", frame_count, directory.display());
+  Ok(())
+}
@@ -0,0 +1,209 @@
+extern crate minifb;
+extern crate raymarcher_ocl_rust;
+
+use minifb::{Key, Window, WindowOptions, KeyRepeat};
+
+use std::time::{Duration, Instant};
+
+use raymarcher_ocl_rust::scene_file::SceneFile;
+use raymarcher_ocl_rust::render_settings::{Integrator, DebugView};
+use raymarcher_ocl_rust::stereo::{self, Stereo, StereoLayout};
+use raymarcher_ocl_rust::camera_path::{CameraPath, Keyframe, Interpolation};
+use raymarcher_ocl_rust::error::Error;
+use raymarcher_ocl_rust::renderer::Renderer;
+
+use crate::camera_controllers::camera_controller::CameraController;
+use crate::camera_controllers::free_fly::FreeFlyController;
+use crate::camera_controllers::orbit::OrbitController;
+use crate::options::Options;
+use crate::render;
+
+const GOD_RAY_SAMPLES: u32 = 16;
+const EXPOSURE_STEP: f32 = 1.1;
+const APERTURE_STEP: f32 = 0.05;
+
+const CAMERA_PATH_FILE: &str = "camera_path.txt";
+// seconds between keyframes while recording a camera path.
+const CAMERA_PATH_RECORD_INTERVAL: f32 = 0.25;
+const SEQUENCE_DIRECTORY: &str = "frames";
+
+/// opens a window on the scene and renders it until escape is pressed or the window is closed.
+pub fn run(renderer: &mut Renderer, scene_file: SceneFile, options: &Options, fps: u32, stats_interval: u64) -> Result<(), Error> {
+  let (width, height) = (renderer.get_width(), renderer.get_height());
+
+  let mut window = Window::new(
+      "Test - ESC to exit",
+      width as usize,
+      height as usize,
+      WindowOptions::default(),
+  )
+  .map_err(|e| Error::Window(e.to_string()))?;
+
+  let SceneFile {scene, mut camera} = scene_file;
+
+  let mut settings = options.settings.clone();
+
+  let mut frames: u64 = 0;
+
+  let mut buffer: Vec<u32> = vec![0; (width * height) as usize];
+
+  let mut controllers: Vec<Box<dyn CameraController>> = vec![Box::new(FreeFlyController::new()), Box::new(OrbitController::new())];
+  let mut active_controller = 0;
+
+  // camera paths being recorded or played back, with when they started.
+  let mut recording: Option<(CameraPath, Instant)> = None;
+  let mut playback: Option<(CameraPath, Instant)> = None;
+
+  // 0 leaves the frame rate unlimited.
+  window.limit_update_rate(if fps == 0 {None} else {Some(Duration::from_micros(1_000_000/fps as u64))});
+
+  //Draw Loop
+  while window.is_open() && !window.is_key_down(Key::Escape) {
+    let start = Instant::now();
+
+    if window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+      active_controller = (active_controller + 1) % controllers.len();
+      controllers[active_controller].activate(&camera, &scene, &settings.quality);
+      println!("using the {} camera controller.", controllers[active_controller].get_name());
+    }
+
+    if window.is_key_pressed(Key::K, KeyRepeat::No) {
+      recording = match recording.take() {
+        None => {
+          println!("recording camera path.");
+          Some((CameraPath::new(Interpolation::CatmullRom), Instant::now()))
+        }
+        Some((mut camera_path, recording_start)) => {
+          camera_path.push(Keyframe::from_camera(&camera, recording_start.elapsed().as_secs_f32()));
+          match camera_path.save(CAMERA_PATH_FILE) {
+            Ok(()) => println!("saved {} keyframes to {}.", camera_path.len(), CAMERA_PATH_FILE),
+            Err(e) => println!("could not save camera path: {}", e)
+          }
+          None
+        }
+      };
+    }
+    if window.is_key_pressed(Key::L, KeyRepeat::No) {
+      playback = match playback.take() {
+        None => match CameraPath::load(CAMERA_PATH_FILE) {
+          Ok(camera_path) => Some((camera_path, Instant::now())),
+          Err(e) => {
+            println!("could not load camera path: {}", e);
+            None
+          }
+        },
+        Some(_) => None
+      };
+    }
+    if window.is_key_pressed(Key::J, KeyRepeat::No) {
+      if let Err(e) = CameraPath::load(CAMERA_PATH_FILE).and_then(|camera_path| render::render_sequence(renderer, &camera, &scene, &settings, &camera_path, SEQUENCE_DIRECTORY)) {
+        println!("could not render camera path: {}", e);
+      }
+    }
+
+    if let Some((camera_path, playback_start)) = &playback {
+      let elapsed = playback_start.elapsed().as_secs_f32();
+      camera_path.apply(&mut camera, camera_path.get_start_time() + elapsed);
+      if elapsed > camera_path.get_duration() {
+        playback = None;
+        controllers[active_controller].activate(&camera, &scene, &settings.quality);
+      }
+    } else {
+      controllers[active_controller].update(&window, &mut camera, &scene, &settings.quality);
+    }
+
+    if let Some((camera_path, recording_start)) = &mut recording {
+      let elapsed = recording_start.elapsed().as_secs_f32();
+      if camera_path.len() == 0 || elapsed - camera_path.get_start_time() - camera_path.get_duration() >= CAMERA_PATH_RECORD_INTERVAL {
+        camera_path.push(Keyframe::from_camera(&camera, elapsed));
+      }
+    }
+
+    if window.is_key_pressed(Key::G, KeyRepeat::No) {
+      settings.god_ray_samples = if settings.god_ray_samples == 0 {GOD_RAY_SAMPLES} else {0};
+    }
+    if window.is_key_pressed(Key::T, KeyRepeat::No) {
+      settings.tone_mapping = settings.tone_mapping.next();
+    }
+    if window.is_key_pressed(Key::R, KeyRepeat::No) {
+      settings.anti_aliasing = settings.anti_aliasing.next();
+    }
+    if window.is_key_pressed(Key::P, KeyRepeat::No) {
+      settings.progressive = !settings.progressive;
+    }
+    if window.is_key_pressed(Key::I, KeyRepeat::No) {
+      settings.integrator = match settings.integrator {
+        Integrator::Whitted => Integrator::PathTracing,
+        Integrator::PathTracing => Integrator::Whitted
+      };
+    }
+    if window.is_key_pressed(Key::M, KeyRepeat::No) {
+      settings.marching = settings.marching.next();
+      println!("{} marching.", settings.marching);
+    }
+    if window.is_key_pressed(Key::O, KeyRepeat::No) {
+      settings.quality = settings.quality.next();
+      println!("{} quality.", settings.quality.get_name());
+    }
+    let debug_keys = [Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6];
+    for (key, debug_view) in debug_keys.iter().zip(DebugView::ALL.iter()) {
+      if window.is_key_pressed(*key, KeyRepeat::No) && settings.debug_view != *debug_view {
+        settings.debug_view = *debug_view;
+        println!("showing {}.", debug_view.get_name());
+      }
+    }
+    if window.is_key_pressed(Key::V, KeyRepeat::No) {
+      camera.set_projection(camera.get_projection().next());
+    }
+    if window.is_key_pressed(Key::RightBracket, KeyRepeat::Yes) {
+      camera.set_aperture(camera.get_aperture() + APERTURE_STEP);
+    }
+    if window.is_key_pressed(Key::LeftBracket, KeyRepeat::Yes) {
+      camera.set_aperture(camera.get_aperture() - APERTURE_STEP);
+    }
+    if window.is_key_pressed(Key::F, KeyRepeat::No) {
+      camera.autofocus(&scene, &settings.quality);
+    }
+    if window.is_key_pressed(Key::B, KeyRepeat::No) {
+      settings.stereo = match settings.stereo.map(|stereo| stereo.layout) {
+        None => Some(Stereo::new(StereoLayout::SideBySide, stereo::DEFAULT_INTEROCULAR_DISTANCE, stereo::DEFAULT_CONVERGENCE_DISTANCE)),
+        Some(StereoLayout::SideBySide) => Some(Stereo::new(StereoLayout::TopBottom, stereo::DEFAULT_INTEROCULAR_DISTANCE, stereo::DEFAULT_CONVERGENCE_DISTANCE)),
+        Some(StereoLayout::TopBottom) => Some(Stereo::new(StereoLayout::Anaglyph, stereo::DEFAULT_INTEROCULAR_DISTANCE, stereo::DEFAULT_CONVERGENCE_DISTANCE)),
+        Some(StereoLayout::Anaglyph) => None
+      };
+    }
+    if window.is_key_pressed(Key::Equal, KeyRepeat::Yes) {
+      settings.exposure *= EXPOSURE_STEP;
+    }
+    if window.is_key_pressed(Key::Minus, KeyRepeat::Yes) {
+      settings.exposure /= EXPOSURE_STEP;
+    }
+
+    //Render Frame
+    // the device renders this frame while the one submitted last time is
+    // read straight into the window buffer. stereo frames are finished when
+    // they are submitted and read straight away.
+    renderer.submit(&camera, &scene, &settings)?;
+    if renderer.get_frames_in_flight() > 1 || renderer.is_frame_ready() {
+      renderer.read_frame(&mut buffer)?;
+    }
+
+    window.update_with_buffer(&buffer, width as usize, height as usize).map_err(|e| Error::Window(e.to_string()))?;
+
+    //Print Stats
+    let duration = start.elapsed().as_millis();
+    let fps = 1000./(duration as f32);
+    if stats_interval != 0 && frames % stats_interval == 0 {
+      let timings = renderer.get_timings();
+      println!("frame {} took {}ms. fps: {}.", frames, duration, fps);
+      println!("upload {:.2}ms, kernel {:.2}ms, readback {:.2}ms.", timings.upload.as_secs_f32()*1000., timings.kernel.as_secs_f32()*1000., timings.readback.as_secs_f32()*1000.);
+      if settings.progressive || settings.is_path_traced() {
+        println!("accumulated {} samples.", renderer.get_sample_count());
+      }
+    }
+
+    frames+=1;
+  }
+
+  Ok(())
+}
@@ -0,0 +1,204 @@
+extern crate ocl;
+
+use std::cmp::Ordering;
+
+use ocl::prm::{Float8, Uint2};
+
+use crate::math::{Vec3, Aabb};
+
+// objects a node holds before it is split.
+const MAX_LEAF_OBJECTS: usize = 4;
+
+/// bounding volume hierarchy over the bounded objects of a scene, so the kernel
+/// only evaluates the objects near a point. nodes are split at the median object
+/// along their longest axis, which keeps the tree depth at log2 of the object count.
+pub struct Bvh {
+  nodes: Vec<Node>,
+  // the unbounded objects first, then the objects of each leaf in turn.
+  indices: Vec<u32>,
+  num_unbounded_objects: u32
+}
+
+// flattened depth first, so the first child of an interior node follows it.
+#[derive(Clone, Copy)]
+struct Node {
+  bounds: Aabb,
+  // the index of the second child for an interior node, the first of its
+  // objects in indices for a leaf.
+  offset: u32,
+  // 0 for an interior node.
+  count: u32
+}
+
+struct Item {
+  index: u32,
+  bounds: Aabb,
+  center: Vec3
+}
+
+impl Bvh {
+  /// builds the tree over the bounds of each object, objects without bounds are
+  /// always evaluated.
+  pub fn build(bounds: &[Option<Aabb>]) -> Self {
+    let mut indices = Vec::with_capacity(bounds.len());
+    let mut items = Vec::with_capacity(bounds.len());
+    for (i, object_bounds) in bounds.iter().enumerate() {
+      match object_bounds {
+        Some(object_bounds) => items.push(Item {index: i as u32, bounds: *object_bounds, center: object_bounds.center()}),
+        None => indices.push(i as u32)
+      }
+    }
+
+    let num_unbounded_objects = indices.len() as u32;
+    let mut bvh = Bvh {nodes: Vec::with_capacity(2*items.len()/MAX_LEAF_OBJECTS + 1), indices: indices, num_unbounded_objects: num_unbounded_objects};
+    if !items.is_empty() {
+      bvh.build_node(&mut items);
+    }
+    bvh
+  }
+
+  /// no tree, every object is evaluated at every point like before there was one.
+  pub fn none(num_objects: usize) -> Self {
+    Bvh {nodes: Vec::new(), indices: (0..num_objects as u32).collect(), num_unbounded_objects: num_objects as u32}
+  }
+
+  fn build_node(&mut self, items: &mut [Item]) -> usize {
+    let bounds = items.iter().fold(Aabb::EMPTY, |bounds, item| bounds.union(&item.bounds));
+    let node_index = self.nodes.len();
+    self.nodes.push(Node {bounds: bounds, offset: 0, count: 0});
+
+    if items.len() <= MAX_LEAF_OBJECTS {
+      self.nodes[node_index].offset = self.indices.len() as u32;
+      self.nodes[node_index].count = items.len() as u32;
+      self.indices.extend(items.iter().map(|item| item.index));
+      return node_index;
+    }
+
+    let axis = items.iter().fold(Aabb::EMPTY, |bounds, item| bounds.grow(item.center)).longest_axis();
+    let middle = items.len()/2;
+    items.select_nth_unstable_by(middle, |a, b| {
+      get_axis(a.center, axis).partial_cmp(&get_axis(b.center, axis)).unwrap_or(Ordering::Equal)
+    });
+
+    let (first, second) = items.split_at_mut(middle);
+    self.build_node(first);
+    let second_index = self.build_node(second);
+    self.nodes[node_index].offset = second_index as u32;
+    node_index
+  }
+
+  pub fn get_num_nodes(&self) -> usize {
+    self.nodes.len()
+  }
+
+  pub fn get_num_unbounded_objects(&self) -> u32 {
+    self.num_unbounded_objects
+  }
+
+  /// the bounds of each node as min in the first three lanes and max in the
+  /// three after the fourth.
+  pub fn get_node_bounds(&self) -> Vec<Float8> {
+    self.nodes.iter().map(|node| {
+      Float8::new(node.bounds.min.x, node.bounds.min.y, node.bounds.min.z, 0.,
+                  node.bounds.max.x, node.bounds.max.y, node.bounds.max.z, 0.)
+    }).collect()
+  }
+
+  /// the offset and count of each node.
+  pub fn get_node_ranges(&self) -> Vec<Uint2> {
+    self.nodes.iter().map(|node| Uint2::new(node.offset, node.count)).collect()
+  }
+
+  pub fn get_indices(&self) -> &[u32] {
+    &self.indices
+  }
+}
+
+fn get_axis(v: Vec3, axis: usize) -> f32 {
+  match axis {
+    0 => v.x,
+    1 => v.y,
+    _ => v.z
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // a mix of boxes of different sizes spread over a few units, every tenth one unbounded.
+  fn test_bounds(count: usize) -> Vec<Option<Aabb>> {
+    (0..count).map(|i| {
+      if i % 10 == 3 {
+        return None;
+      }
+      let center = Vec3::new((i*7 % 13) as f32, (i*3 % 5) as f32, (i % 11) as f32*0.5);
+      Some(Aabb::from_center_half_extents(center, Vec3::splat(0.1 + (i % 3) as f32*0.2)))
+    }).collect()
+  }
+
+  // the bounds, offset and count of a node as the kernel reads them.
+  fn node(bvh: &Bvh, index: usize) -> (Aabb, u32, u32) {
+    let bounds = bvh.get_node_bounds()[index];
+    let range = bvh.get_node_ranges()[index];
+    (Aabb::new(Vec3::new(bounds[0], bounds[1], bounds[2]), Vec3::new(bounds[4], bounds[5], bounds[6])), range[0], range[1])
+  }
+
+  fn inside(inner: &Aabb, outer: &Aabb) -> bool {
+    inner.min.max(outer.min) == inner.min && inner.max.min(outer.max) == inner.max
+  }
+
+  // walks the tree from index, checking each child is inside its parent and
+  // collecting the objects of the leaves.
+  fn walk(bvh: &Bvh, index: usize, objects: &mut Vec<u32>) {
+    let (bounds, offset, count) = node(bvh, index);
+    if count > 0 {
+      objects.extend_from_slice(&bvh.get_indices()[offset as usize..(offset + count) as usize]);
+      return;
+    }
+    for child in [index + 1, offset as usize].iter() {
+      assert!(inside(&node(bvh, *child).0, &bounds));
+      walk(bvh, *child, objects);
+    }
+  }
+
+  #[test]
+  fn unbounded_objects_first() {
+    let bounds = test_bounds(40);
+    let bvh = Bvh::build(&bounds);
+    let unbounded: Vec<u32> = (0..40).filter(|&i| bounds[i as usize].is_none()).collect();
+    assert_eq!(bvh.get_num_unbounded_objects() as usize, unbounded.len());
+    assert_eq!(&bvh.get_indices()[..unbounded.len()], unbounded.as_slice());
+
+    let none = Bvh::none(5);
+    assert_eq!(none.get_num_nodes(), 0);
+    assert_eq!(none.get_indices(), &[0, 1, 2, 3, 4]);
+  }
+
+  #[test]
+  fn every_object_in_one_leaf() {
+    let bounds = test_bounds(57);
+    let bvh = Bvh::build(&bounds);
+    let mut objects = Vec::new();
+    walk(&bvh, 0, &mut objects);
+    objects.sort();
+    let bounded: Vec<u32> = (0..57).filter(|&i| bounds[i as usize].is_some()).collect();
+    assert_eq!(objects, bounded);
+    assert_eq!(bvh.get_indices().len(), bounds.len());
+  }
+
+  #[test]
+  fn children_inside_parents() {
+    let bounds = test_bounds(100);
+    let bvh = Bvh::build(&bounds);
+    // walk checks every child against its parent.
+    walk(&bvh, 0, &mut Vec::new());
+    // and the leaves hold the bounds of their objects.
+    for index in 0..bvh.get_num_nodes() {
+      let (node_bounds, offset, count) = node(&bvh, index);
+      for &object in &bvh.get_indices()[offset as usize..(offset + count) as usize] {
+        assert!(inside(bounds[object as usize].as_ref().unwrap(), &node_bounds));
+      }
+    }
+  }
+}
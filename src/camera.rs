@@ -1,158 +1,255 @@
 extern crate ocl;
-extern crate fast_inv_sqrt;
 
-use ocl::prm::{Float8};
+use ocl::prm::{Float16};
 
-use crate::vector3::Vector3;
+use crate::math::{Vec3, Mat3, Quat};
+use crate::projection::Projection;
+use crate::scene::Scene;
+use crate::render_settings::Quality;
 
+const WORLD_UP: Vec3 = Vec3::Y;
+
+#[derive(Clone)]
 pub struct Camera {
-  position: (f32, f32, f32),
-  look_dir: (f32, f32, f32),
-  right_dir: (f32, f32, f32),
-  up_dir: (f32, f32, f32),
-  rotation: (f32, f32, f32),
-  rotation_sin: (f32, f32, f32),
-  rotation_cos: (f32, f32, f32),
-  frame_distance: f32,
-  scale:f32
+  position: Vec3,
+  orientation: Quat,
+  look_dir: Vec3,
+  right_dir: Vec3,
+  up_dir: Vec3,
+  projection: Projection,
+  aperture: f32,
+  focus_distance: f32,
+  view_shift: f32
 }
 
 impl Camera {
-  pub fn new(position: (f32, f32, f32), rotation: (f32, f32, f32), frame_distance: f32, scale: f32) -> Self {
+  pub fn new(position: Vec3, rotation: Vec3, projection: Projection) -> Self {
     let mut camera = Camera {position: position, 
-                        look_dir: (0.,0.,0.), 
-                        right_dir: (0.,0.,0.), 
-                        up_dir: (0.,0.,0.), 
-                        rotation: rotation, 
-                        rotation_sin: (0., 0., 0.),
-                        rotation_cos: (0., 0., 0.),
-                        frame_distance: frame_distance, 
-                        scale: scale};
+                        orientation: Quat::from_euler(rotation),
+                        look_dir: Vec3::Z, 
+                        right_dir: Vec3::X, 
+                        up_dir: Vec3::Y, 
+                        projection: projection,
+                        aperture: 0.,
+                        focus_distance: 10.,
+                        view_shift: 0.};
     camera.calculate_rotation_info();
     camera
   }
   fn calculate_rotation_info(&mut self) {
-    self.calculate_trig_funcs();
-    self.calculate_forward_vec();
-    self.calculate_right_vec();
-    self.calculate_up_vec();
-  }
-  fn calculate_trig_funcs(&mut self) {
-    self.rotation_cos = (self.rotation.0.cos(),self.rotation.1.cos(),self.rotation.2.cos());
-    self.rotation_sin = (self.rotation.0.sin(),self.rotation.1.sin(),self.rotation.2.sin());
-  }
-  fn calculate_forward_vec(&mut self) {
-    let x: f32 = self.rotation_cos.2*self.rotation_sin.1*self.rotation_cos.0 + self.rotation_sin.2*self.rotation_sin.0;
-    let y: f32 = self.rotation_sin.2*self.rotation_sin.1*self.rotation_cos.0 - self.rotation_cos.2*self.rotation_sin.0;
-    let z: f32 = self.rotation_cos.1*self.rotation_cos.0;
-
-    self.look_dir = (x,y,z);
-  }
-  fn calculate_right_vec(&mut self) {
-    let x: f32 = self.rotation_cos.2*self.rotation_cos.1;
-    let y: f32 = self.rotation_sin.2*self.rotation_cos.1;
-    let z: f32 = -self.rotation_sin.1;
-
-    self.right_dir = (x,y,z);
-  }
-  fn calculate_up_vec(&mut self) {
-    let x: f32 = self.rotation_cos.2*self.rotation_sin.1*self.rotation_sin.0 - self.rotation_sin.2*self.rotation_cos.0;
-    let y: f32 = self.rotation_sin.2*self.rotation_sin.1*self.rotation_sin.0 + self.rotation_cos.2*self.rotation_cos.0;
-    let z: f32 = self.rotation_cos.1*self.rotation_sin.0;
-
-    self.up_dir = (x,y,z);
+    // renormalize so rounding errors don't build up over many small rotations
+    self.orientation = self.orientation.normalize();
+    self.look_dir = self.orientation*Vec3::Z;
+    self.right_dir = self.orientation*Vec3::X;
+    self.up_dir = self.orientation*Vec3::Y;
+  }
+  fn from_yaw_pitch_roll(yaw: f32, pitch: f32, roll: f32) -> Quat {
+    Quat::from_axis_angle(WORLD_UP, yaw)*Quat::from_axis_angle(Vec3::X, pitch)*Quat::from_axis_angle(Vec3::Z, roll)
+  }
+  /// yaw is around the world up axis, pitch is positive looking down and roll is around the look direction.
+  pub fn get_yaw_pitch_roll(&self) -> (f32, f32, f32) {
+    let yaw = self.look_dir.x.atan2(self.look_dir.z);
+    let pitch = (-self.look_dir.y).max(-1.).min(1.).asin();
+    let roll = self.right_dir.y.atan2(self.up_dir.y);
+    (yaw, pitch, roll)
   }
   pub fn move_forward(&mut self, dist: f32) {
-    self.position = self.position.add(self.look_dir.scale(dist));
+    self.position += self.look_dir*dist;
   }
   pub fn move_right(&mut self, dist: f32) {
-    self.position = self.position.add(self.right_dir.scale(dist));
+    self.position += self.right_dir*dist;
   }
   pub fn move_up(&mut self, dist: f32) {
-    self.position = self.position.add(self.up_dir.scale(dist));
+    self.position += self.up_dir*dist;
   }
-  pub fn set_position(&mut self, pos: (f32, f32, f32)) {
+  pub fn set_position(&mut self, pos: Vec3) {
     self.position = pos;
   }
+  pub fn get_position(&self) -> Vec3 {
+    self.position
+  }
+  pub fn get_look_dir(&self) -> Vec3 {
+    self.look_dir
+  }
+  pub fn get_right_dir(&self) -> Vec3 {
+    self.right_dir
+  }
+  pub fn get_up_dir(&self) -> Vec3 {
+    self.up_dir
+  }
   pub fn pitch(&mut self, rad: f32){
-    let curr_pitch = self.rotation.0/self.rotation_cos.1;
-    
-    let mut anglex = (curr_pitch + rad)*self.rotation_cos.1;
-    anglex = if self.rotation_cos.1 < 0. {-anglex} else {anglex};
-    
-    let anglez = -(curr_pitch + rad)*self.rotation_sin.1;
-
-    self.rotation.0 = anglex;
-    self.rotation.2 = anglez;
+    self.orientation = self.orientation*Quat::from_axis_angle(Vec3::X, rad);
     self.calculate_rotation_info();
   }
   pub fn yaw(&mut self, rad: f32){
-    self.rotation.1 += rad;
+    self.orientation = Quat::from_axis_angle(WORLD_UP, rad)*self.orientation;
     self.calculate_rotation_info();
   }
   pub fn roll(&mut self, rad: f32){
-
+    self.orientation = self.orientation*Quat::from_axis_angle(Vec3::Z, rad);
+    self.calculate_rotation_info();
+  }
+  pub fn set_yaw_pitch_roll(&mut self, yaw: f32, pitch: f32, roll: f32) {
+    self.orientation = Camera::from_yaw_pitch_roll(yaw, pitch, roll);
+    self.calculate_rotation_info();
   }
   pub fn set_pitch(&mut self, rad: f32) {
-    let mut anglex = rad*self.rotation_cos.1;
-    anglex = if self.rotation_cos.1 < 0. {-anglex} else {anglex};
-    
-    let anglez = -rad*self.rotation_sin.1;
-
-    self.rotation.0 = anglex;
-    self.rotation.2 = anglez;
+    let (yaw, _, roll) = self.get_yaw_pitch_roll();
+    self.orientation = Camera::from_yaw_pitch_roll(yaw, rad, roll);
     self.calculate_rotation_info();
   }
   pub fn set_yaw(&mut self, rad: f32) {
-    self.rotation.1 = rad;
+    let (_, pitch, roll) = self.get_yaw_pitch_roll();
+    self.orientation = Camera::from_yaw_pitch_roll(rad, pitch, roll);
     self.calculate_rotation_info();
   }
   pub fn set_roll(&mut self, rad: f32) {
-    let mut anglex = rad*self.rotation_sin.1;
-    anglex = if self.rotation_sin.1 < 0. {-anglex} else {anglex};
-    
-    let anglez = -rad*self.rotation_cos.1;
-
-    self.rotation.0 = anglex;
-    self.rotation.2 = anglez;
+    let (yaw, pitch, _) = self.get_yaw_pitch_roll();
+    self.orientation = Camera::from_yaw_pitch_roll(yaw, pitch, rad);
     self.calculate_rotation_info();
   }
-  pub fn set_rotation(&mut self, rads: (f32, f32, f32)) {
-    self.rotation = rads;
+  /// rotation around x then y then z, the same convention as the scene objects.
+  pub fn set_rotation(&mut self, rads: Vec3) {
+    self.orientation = Quat::from_euler(rads);
     self.calculate_rotation_info();
   }
-  pub fn look_at(&mut self, point: (f32, f32, f32)){
-    let to_point = point.sub(self.position).fast_normalize();
-
-    //for yaw find the rotation between the positive z axis and the point
-    let to_pointxz = (to_point.0, 0., to_point.2).fast_normalize();
-    let mut angley = to_pointxz.2.acos();
-    let crossy = (0., 0., 1.).cross(to_pointxz);
-    angley = if crossy.1 < 0. {-angley} else {angley};
-    self.rotation.1 = angley;
+  pub fn set_orientation(&mut self, orientation: Quat) {
+    self.orientation = orientation;
     self.calculate_rotation_info();
-    
-    // for pitch find the rotation between the look vector projected on the xz axis
-    // and the point projected onto the yz axis.
-    let to_point_look_up = to_point.proj_onto_plane(self.right_dir).fast_normalize();
-    let lookxz = (self.look_dir.0, 0., self.look_dir.2).fast_normalize();
-    let mut angle_look = lookxz.dot(to_point_look_up).acos();
-    let crossx = lookxz.cross(to_point_look_up);
-    angle_look = if self.right_dir.dot(crossx) < 0. {-angle_look} else {angle_look};
-
-    // the x pitch is zero when the dot product between the z axis and the point is zero.
-    // it is angle_look when the dot product between the z axis and the point is one.
-    let mut anglex = angle_look*to_point.2;
-    // when the point is facing the other way in the x direction, invert the x angle.
-    anglex = if to_point.2 < 0. {-anglex} else {anglex};
-    
-    // the z pitch is zero when the dot produce between the x axis and the point is zero.
-    // it is angle_look when the dot product between the x axis and the point is one.
-    let anglez = -angle_look*to_point.0;
+  }
+  pub fn get_orientation(&self) -> Quat {
+    self.orientation
+  }
+  pub fn set_projection(&mut self, projection: Projection) {
+    self.projection = projection;
+  }
+  pub fn get_projection(&self) -> Projection {
+    self.projection
+  }
+  /// radius of the thin lens, 0 is a pinhole with everything in focus.
+  pub fn set_aperture(&mut self, aperture: f32) {
+    self.aperture = aperture.max(0.);
+  }
+  pub fn get_aperture(&self) -> f32 {
+    self.aperture
+  }
+  /// distance along the look direction of the plane in focus. fisheye and
+  /// equirectangular focus on the sphere of this radius around the lens instead.
+  pub fn set_focus_distance(&mut self, focus_distance: f32) {
+    self.focus_distance = focus_distance;
+  }
+  pub fn get_focus_distance(&self) -> f32 {
+    self.focus_distance
+  }
+  /// focuses on whatever is under the center of the screen, returns false
+  /// and leaves the focus unchanged if nothing is hit.
+  pub fn autofocus(&mut self, scene: &Scene, quality: &Quality) -> bool {
+    match scene.raycast(self.position, self.look_dir, quality) {
+      Some((point, _)) => {
+        self.focus_distance = (point - self.position).dot(self.look_dir);
+        true
+      }
+      None => false
+    }
+  }
+  /// copy of the camera moved offset along its right direction, with its view
+  /// sheared by shift times the distance along the look direction.
+  pub fn eye(&self, offset: f32, shift: f32) -> Camera {
+    let mut eye = self.clone();
+    eye.position = self.position + self.right_dir*offset;
+    eye.view_shift = shift;
+    eye
+  }
+  pub fn look_at(&mut self, point: Vec3){
+    let forward = (point - self.position).normalize();
+    // keep the current right direction when looking straight up or down.
+    let right = match WORLD_UP.cross(forward) {
+      right if right.length_squared() > 1e-8 => right.normalize(),
+      _ => self.right_dir.proj_onto_plane(forward).normalize()
+    };
 
-    self.set_rotation((anglex, angley, anglez));
+    self.orientation = Quat::from_mat3(&Mat3::from_cols(right, forward.cross(right), forward));
+    self.calculate_rotation_info();
   }
-  pub fn get_data(&self) -> Float8 {
-    return Float8::new(self.position.0,self.position.1, self.position.2,self.rotation.0,self.rotation.1, self.rotation.2, self.frame_distance, self.scale)
+  /// the point every primary ray passes through, when the rays through a rectangle
+  /// of pixels are bounded by the planes through its corner rays. None for the
+  /// curved projections, orthographic rays and a lens with an aperture.
+  pub fn get_frustum_apex(&self) -> Option<Vec3> {
+    if self.aperture > 0. {
+      return None;
+    }
+    match self.projection {
+      // the rays start on the frame but all come from behind it.
+      Projection::Legacy {frame_distance, ..} => Some(self.position - self.right_dir*self.view_shift*frame_distance - self.look_dir*frame_distance),
+      Projection::Perspective {..} => Some(self.position),
+      _ => None
+    }
+  }
+  /// angle in radians between the rays through neighbouring pixels at the center
+  /// of the image. 0 for orthographic rays, which are a fixed size apart instead.
+  pub fn get_pixel_angle(&self, width: u32, height: u32) -> f32 {
+    match self.projection {
+      Projection::Legacy {frame_distance, scale} => 1./(scale*frame_distance),
+      Projection::Perspective {fov, ..} => 2.*(fov.to_radians()/2.).tan()/height as f32,
+      Projection::Orthographic {..} => 0.,
+      Projection::Fisheye {fov} => fov.to_radians()/width.min(height) as f32,
+      Projection::Equirectangular => std::f32::consts::PI/height as f32
+    }
+  }
+  /// start point and direction of the pinhole ray through a pixel, the same as the
+  /// kernel's. None if the projection does not cover the pixel.
+  pub fn get_ray(&self, pixel: (f32, f32), width: u32, height: u32) -> Option<(Vec3, Vec3)> {
+    let (width, height) = (width as f32, height as f32);
+    // camera space to world space, x right, y up and z forward.
+    let basis = Mat3::from_cols(self.right_dir, self.up_dir, self.look_dir);
+
+    match self.projection {
+      Projection::Legacy {frame_distance, scale} => {
+        let offx = (pixel.0 - width/2.)/scale;
+        let offy = (height/2. - pixel.1)/scale;
+        let direction = basis*Vec3::new(offx + self.view_shift*frame_distance, offy, frame_distance);
+        Some((self.position + basis*Vec3::new(offx, offy, 0.), direction.normalize()))
+      }
+      Projection::Perspective {fov, aspect, near} => {
+        let tan_half_fov = (fov.to_radians()/2.).tan();
+        let aspect = aspect.unwrap_or(width/height);
+        let ndcx = 2.*(pixel.0 + 0.5)/width - 1.;
+        let ndcy = 1. - 2.*(pixel.1 + 0.5)/height;
+        let direction = (basis*Vec3::new(ndcx*tan_half_fov*aspect + self.view_shift, ndcy*tan_half_fov, 1.)).normalize();
+        Some((self.position + direction*(near/direction.dot(self.look_dir)), direction))
+      }
+      Projection::Orthographic {height: view_height} => {
+        let offx = (pixel.0 + 0.5 - width/2.)*view_height/height;
+        let offy = (height/2. - pixel.1 - 0.5)*view_height/height;
+        Some((self.position + basis*Vec3::new(offx, offy, 0.), self.look_dir))
+      }
+      Projection::Fisheye {fov} => {
+        let radius = width.min(height)/2.;
+        let u = (pixel.0 + 0.5 - width/2.)/radius;
+        let v = (height/2. - pixel.1 - 0.5)/radius;
+        let r = (u*u + v*v).sqrt();
+        if r > 1. {
+          return None;
+        }
+        let theta = r*fov.to_radians()/2.;
+        let phi = v.atan2(u);
+        let direction = basis*Vec3::new(theta.sin()*phi.cos(), theta.sin()*phi.sin(), theta.cos());
+        Some((self.position, direction.normalize()))
+      }
+      Projection::Equirectangular => {
+        let longitude = (2.*(pixel.0 + 0.5)/width - 1.)*std::f32::consts::PI;
+        let latitude = (1. - 2.*(pixel.1 + 0.5)/height)*std::f32::consts::FRAC_PI_2;
+        let direction = basis*Vec3::new(latitude.cos()*longitude.sin(), latitude.sin(), latitude.cos()*longitude.cos());
+        Some((self.position, direction.normalize()))
+      }
+    }
+  }
+  pub fn get_data(&self) -> Float16 {
+    let parameters = self.projection.get_parameters();
+    Float16::new(self.position.x, self.position.y, self.position.z,
+      self.right_dir.x, self.right_dir.y, self.right_dir.z,
+      self.up_dir.x, self.up_dir.y, self.up_dir.z,
+      self.projection.get_key() as f32, parameters.0, parameters.1, parameters.2,
+      self.aperture, self.focus_distance, self.view_shift)
   }
-}
\ No newline at end of file
+}
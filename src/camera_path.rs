@@ -0,0 +1,316 @@
+use std::fs;
+use std::path::Path;
+
+use crate::camera::Camera;
+use crate::error::Error;
+use crate::projection::Projection;
+use crate::math::{Vec3, Quat};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum KeyframeOrientation {
+  Rotation(Quat),
+  /// look at a point from the keyframe position.
+  LookAt(Vec3)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Keyframe {
+  pub time: f32,
+  pub position: Vec3,
+  pub orientation: KeyframeOrientation,
+  /// vertical field of view in degrees, only used by perspective cameras.
+  pub fov: f32
+}
+
+impl Keyframe {
+  pub fn from_camera(camera: &Camera, time: f32) -> Self {
+    let fov = match camera.get_projection() {
+      Projection::Perspective {fov, ..} => fov,
+      _ => DEFAULT_FOV
+    };
+    Keyframe {time: time,
+              position: camera.get_position(),
+              orientation: KeyframeOrientation::Rotation(camera.get_orientation()),
+              fov: fov}
+  }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Interpolation {
+  /// passes through every keyframe.
+  CatmullRom,
+  /// a cubic bezier curve per pair of keyframes with its control points
+  /// placed among the neighbouring keyframes, a uniform b-spline. smoother
+  /// but only passes through the first and last keyframe.
+  Bezier
+}
+
+const DEFAULT_FOV: f32 = 60.;
+
+pub struct CameraPath {
+  keyframes: Vec<Keyframe>,
+  interpolation: Interpolation
+}
+
+impl CameraPath {
+  pub fn new(interpolation: Interpolation) -> Self {
+    CameraPath {keyframes: Vec::new(), interpolation: interpolation}
+  }
+
+  /// keyframes are kept sorted by time.
+  pub fn push(&mut self, keyframe: Keyframe) {
+    let index = self.keyframes.iter().position(|k| k.time > keyframe.time).unwrap_or(self.keyframes.len());
+    self.keyframes.insert(index, keyframe);
+  }
+
+  pub fn len(&self) -> usize {
+    self.keyframes.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.keyframes.is_empty()
+  }
+
+  pub fn get_start_time(&self) -> f32 {
+    self.keyframes.first().map_or(0., |k| k.time)
+  }
+
+  pub fn get_duration(&self) -> f32 {
+    self.keyframes.last().map_or(0., |k| k.time) - self.get_start_time()
+  }
+
+  /// moves the camera to where the path is at time, clamped to the ends of the path.
+  pub fn apply(&self, camera: &mut Camera, time: f32) {
+    if self.keyframes.is_empty() {
+      return;
+    }
+
+    // segment containing time and how far along it time is.
+    let segment = self.keyframes.iter().rposition(|k| k.time <= time).unwrap_or(0).min(self.keyframes.len() - 1);
+    let next = (segment + 1).min(self.keyframes.len() - 1);
+    let (k1, k2) = (&self.keyframes[segment], &self.keyframes[next]);
+    let t = if k2.time > k1.time {((time - k1.time)/(k2.time - k1.time)).max(0.).min(1.)} else {0.};
+
+    let position = match self.interpolation {
+      Interpolation::CatmullRom => {
+        let k0 = &self.keyframes[segment.saturating_sub(1)];
+        let k3 = &self.keyframes[(next + 1).min(self.keyframes.len() - 1)];
+        catmull_rom(k0.position, k1.position, k2.position, k3.position, t)
+      }
+      Interpolation::Bezier => {
+        // past the ends the keyframes are reflected about the end ones, so the
+        // curve starts and ends on them.
+        let n = self.keyframes.len() as isize;
+        let clamped = |i: isize| self.keyframes[i.max(0).min(n - 1) as usize].position;
+        let point = |i: isize| {
+          if i < 0 {
+            clamped(0)*2. - clamped(-i)
+          } else if i >= n {
+            clamped(n - 1)*2. - clamped(2*(n - 1) - i)
+          } else {
+            clamped(i)
+          }
+        };
+        let i = segment as isize;
+        let (p0, p1, p2, p3) = (point(i - 1), point(i), point(i + 1), point(i + 2));
+        cubic_bezier((p0 + p1*4. + p2)/6., (p1*2. + p2)/3., (p1 + p2*2.)/3., (p1 + p2*4. + p3)/6., t)
+      }
+    };
+    camera.set_position(position);
+
+    match (k1.orientation, k2.orientation) {
+      (KeyframeOrientation::LookAt(a), KeyframeOrientation::LookAt(b)) => {
+        camera.look_at(a.lerp(b, t));
+      }
+      _ => {
+        let a = orientation_at(camera, k1);
+        let b = orientation_at(camera, k2);
+        camera.set_orientation(a.slerp(b, t));
+      }
+    }
+
+    if let Projection::Perspective {aspect, near, ..} = camera.get_projection() {
+      camera.set_projection(Projection::Perspective {fov: k1.fov + (k2.fov - k1.fov)*t, aspect: aspect, near: near});
+    }
+  }
+
+  /// text format, one item per line and # starts a comment:
+  ///   interpolation catmull-rom|bezier
+  ///   keyframe <time> <x> <y> <z> rotation <w> <x> <y> <z> <fov>
+  ///   keyframe <time> <x> <y> <z> look_at <x> <y> <z> <fov>
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+    let mut camera_path = CameraPath::new(Interpolation::CatmullRom);
+    for (line_number, line) in fs::read_to_string(path)?.lines().enumerate() {
+      let line = line.split('#').next().unwrap_or("");
+      let tokens: Vec<&str> = line.split_whitespace().collect();
+      let parse_error = |message: &str| Error::Parse(format!("line {}: {}", line_number + 1, message));
+
+      match tokens.first() {
+        None => continue,
+        Some(&"interpolation") => {
+          camera_path.interpolation = match tokens.get(1) {
+            Some(&"catmull-rom") => Interpolation::CatmullRom,
+            Some(&"bezier") => Interpolation::Bezier,
+            _ => return Err(parse_error("expected catmull-rom or bezier"))
+          };
+        }
+        Some(&"keyframe") => {
+          let numbers = |range: std::ops::Range<usize>| -> Result<Vec<f32>, Error> {
+            tokens.get(range).ok_or_else(|| parse_error("too few values"))?
+              .iter()
+              .map(|token| token.parse::<f32>().map_err(|_| parse_error(&format!("{} is not a number", token))))
+              .collect()
+          };
+          let values = numbers(1..5)?;
+          let (orientation, fov) = match tokens.get(5) {
+            Some(&"rotation") => {
+              let q = numbers(6..11)?;
+              (KeyframeOrientation::Rotation(Quat::new(q[1], q[2], q[3], q[0]).normalize()), q[4])
+            }
+            Some(&"look_at") => {
+              let l = numbers(6..10)?;
+              (KeyframeOrientation::LookAt(Vec3::new(l[0], l[1], l[2])), l[3])
+            }
+            _ => return Err(parse_error("expected rotation or look_at"))
+          };
+          camera_path.push(Keyframe {time: values[0],
+                                    position: Vec3::new(values[1], values[2], values[3]),
+                                    orientation: orientation,
+                                    fov: fov});
+        }
+        Some(other) => return Err(parse_error(&format!("unknown item {}", other)))
+      }
+    }
+    Ok(camera_path)
+  }
+
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+    let mut out = String::from(match self.interpolation {
+      Interpolation::CatmullRom => "interpolation catmull-rom\n",
+      Interpolation::Bezier => "interpolation bezier\n"
+    });
+    for k in &self.keyframes {
+      let orientation = match k.orientation {
+        KeyframeOrientation::Rotation(q) => {
+          format!("rotation {} {} {} {}", q.w, q.x, q.y, q.z)
+        }
+        KeyframeOrientation::LookAt(l) => format!("look_at {} {} {}", l.x, l.y, l.z)
+      };
+      out.push_str(&format!("keyframe {} {} {} {} {} {}\n", k.time, k.position.x, k.position.y, k.position.z, orientation, k.fov));
+    }
+    fs::write(path, out)?;
+    Ok(())
+  }
+}
+
+fn orientation_at(camera: &Camera, keyframe: &Keyframe) -> Quat {
+  match keyframe.orientation {
+    KeyframeOrientation::Rotation(q) => q,
+    KeyframeOrientation::LookAt(point) => {
+      let mut look = camera.clone();
+      look.set_position(keyframe.position);
+      look.look_at(point);
+      look.get_orientation()
+    }
+  }
+}
+
+/// uniform catmull-rom spline between p1 and p2.
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+  let t2 = t*t;
+  let t3 = t2*t;
+  (p1*2. + (p2 - p0)*t + (p0*2. - p1*5. + p2*4. - p3)*t2 + (p1*3. - p0 - p2*3. + p3)*t3)*0.5
+}
+
+/// cubic bezier curve from p0 to p3.
+fn cubic_bezier(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+  let u = 1. - t;
+  p0*(u*u*u) + p1*(3.*u*u*t) + p2*(3.*u*t*t) + p3*(t*t*t)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const EPSILON: f32 = 1e-5;
+
+  fn keyframe(time: f32, position: Vec3, orientation: KeyframeOrientation, fov: f32) -> Keyframe {
+    Keyframe {time: time, position: position, orientation: orientation, fov: fov}
+  }
+
+  fn get_fov(camera: &Camera) -> f32 {
+    match camera.get_projection() {
+      Projection::Perspective {fov, ..} => fov,
+      _ => panic!("not a perspective camera")
+    }
+  }
+
+  #[test]
+  fn save_and_load() {
+    let mut camera_path = CameraPath::new(Interpolation::Bezier);
+    let rotation = Quat::from_axis_angle(Vec3::new(1., 2., 3.).normalize(), 0.7);
+    camera_path.push(keyframe(0.5, Vec3::new(1., -2., 3.5), KeyframeOrientation::Rotation(rotation), 45.));
+    camera_path.push(keyframe(1.75, Vec3::new(-4., 0.25, 6.), KeyframeOrientation::LookAt(Vec3::new(0., 1., 0.)), 70.));
+
+    let path = std::env::temp_dir().join(format!("camera_path_test_{}.txt", std::process::id()));
+    let saved = camera_path.save(&path).is_ok();
+    let loaded = CameraPath::load(&path);
+    let _ = fs::remove_file(&path);
+    assert!(saved);
+    let loaded = loaded.ok().unwrap();
+
+    assert!(loaded.interpolation == Interpolation::Bezier);
+    assert_eq!(loaded.len(), 2);
+    for (a, b) in camera_path.keyframes.iter().zip(&loaded.keyframes) {
+      assert!((a.time - b.time).abs() < EPSILON);
+      assert!((a.position - b.position).length() < EPSILON);
+      assert!((a.fov - b.fov).abs() < EPSILON);
+      match (a.orientation, b.orientation) {
+        (KeyframeOrientation::Rotation(a), KeyframeOrientation::Rotation(b)) => {
+          assert!((a.x - b.x).abs() < EPSILON && (a.y - b.y).abs() < EPSILON && (a.z - b.z).abs() < EPSILON && (a.w - b.w).abs() < EPSILON);
+        }
+        (KeyframeOrientation::LookAt(a), KeyframeOrientation::LookAt(b)) => assert!((a - b).length() < EPSILON),
+        _ => panic!("orientation changed form")
+      }
+    }
+  }
+
+  #[test]
+  fn rotation_is_w_first() {
+    let path = std::env::temp_dir().join(format!("camera_path_w_test_{}.txt", std::process::id()));
+    fs::write(&path, "# identity\nkeyframe 0 1 2 3 rotation 1 0 0 0 60\n").ok().unwrap();
+    let loaded = CameraPath::load(&path);
+    let _ = fs::remove_file(&path);
+    match loaded.ok().unwrap().keyframes[0].orientation {
+      KeyframeOrientation::Rotation(q) => assert!((q.w - 1.).abs() < EPSILON && q.x.abs() < EPSILON),
+      _ => panic!("expected a rotation")
+    }
+  }
+
+  #[test]
+  fn bezier_keeps_orientation_with_position() {
+    let mut camera_path = CameraPath::new(Interpolation::Bezier);
+    let forward = KeyframeOrientation::Rotation(Quat::from_axis_angle(Vec3::new(0., 1., 0.), 0.));
+    let turned = KeyframeOrientation::Rotation(Quat::from_axis_angle(Vec3::new(0., 1., 0.), 1.));
+    camera_path.push(keyframe(0., Vec3::new(0., 0., 0.), forward, 40.));
+    camera_path.push(keyframe(1., Vec3::new(3., 0., 0.), turned, 60.));
+    camera_path.push(keyframe(2., Vec3::new(3., 0., 3.), forward, 80.));
+    camera_path.push(keyframe(3., Vec3::new(6., 0., 3.), turned, 100.));
+    let mut camera = Camera::new(Vec3::ZERO, Vec3::new(0., 0., 1.), Projection::perspective(60.));
+
+    // the curve starts and ends on the end keyframes.
+    camera_path.apply(&mut camera, -1.);
+    assert!(camera.get_position().length() < EPSILON);
+    assert!((get_fov(&camera) - 40.).abs() < EPSILON);
+    camera_path.apply(&mut camera, 5.);
+    assert!((camera.get_position() - Vec3::new(6., 0., 3.)).length() < EPSILON);
+    assert!((get_fov(&camera) - 100.).abs() < EPSILON);
+
+    // at a keyframe time the position is the b-spline point of that keyframe and
+    // the fov and orientation are the keyframe's own.
+    camera_path.apply(&mut camera, 2.);
+    assert!((camera.get_position() - (Vec3::new(3., 0., 0.) + Vec3::new(3., 0., 3.)*4. + Vec3::new(6., 0., 3.))/6.).length() < EPSILON);
+    assert!((get_fov(&camera) - 80.).abs() < EPSILON);
+    assert!((camera.get_look_dir() - Vec3::new(0., 0., 1.)).length() < 1e-4);
+  }
+}
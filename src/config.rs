@@ -0,0 +1,64 @@
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::devices::Selector;
+use crate::error::Error;
+
+/// settings kept between runs, in a text file of `key = value` lines.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Config {
+  pub platform: Option<Selector>,
+  pub device: Option<Selector>
+}
+
+impl Config {
+  /// $XDG_CONFIG_HOME/raymarcher/config, falling back to ~/.config/raymarcher/config.
+  pub fn default_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+      .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("raymarcher").join("config"))
+  }
+
+  /// a missing file is an empty config.
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+    let source = match fs::read_to_string(path) {
+      Ok(source) => source,
+      Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+      Err(e) => return Err(Error::from(e))
+    };
+
+    let mut config = Config::default();
+    for (line_number, line) in source.lines().enumerate() {
+      let line = line.split('#').next().unwrap_or("").trim();
+      if line.is_empty() {
+        continue;
+      }
+      let mut parts = line.splitn(2, '=').map(str::trim);
+      match (parts.next(), parts.next()) {
+        (Some("platform"), Some(value)) => config.platform = Some(value.parse()?),
+        (Some("device"), Some(value)) => config.device = Some(value.parse()?),
+        _ => return Err(Error::Parse(format!("line {}: expected platform = <selector> or device = <selector>", line_number + 1)))
+      }
+    }
+    Ok(config)
+  }
+
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+    let path = path.as_ref();
+    if let Some(directory) = path.parent() {
+      fs::create_dir_all(directory)?;
+    }
+
+    let mut out = String::new();
+    if let Some(platform) = &self.platform {
+      out.push_str(&format!("platform = {}\n", platform));
+    }
+    if let Some(device) = &self.device {
+      out.push_str(&format!("device = {}\n", device));
+    }
+    fs::write(path, out)?;
+    Ok(())
+  }
+}
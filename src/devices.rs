@@ -0,0 +1,172 @@
+extern crate ocl;
+
+use std::fmt;
+use std::str::FromStr;
+
+use ocl::{Platform, Device};
+use ocl::flags::DeviceType;
+use ocl::enums::{DeviceInfo as InfoKind, DeviceInfoResult};
+
+use crate::error::Error;
+
+/// picks a platform or device by its index, a case insensitive substring of its
+/// name or, for devices, its type.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Selector {
+  Index(usize),
+  Name(String),
+  Type(DeviceType)
+}
+
+/// a number is an index, gpu, cpu and accelerator are types and anything else
+/// is part of a name.
+impl FromStr for Selector {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self, Error> {
+    if s.is_empty() {
+      return Err(Error::Parse(String::from("empty device selector")));
+    }
+    Ok(match s.to_lowercase().as_str() {
+      "gpu" => Selector::Type(DeviceType::GPU),
+      "cpu" => Selector::Type(DeviceType::CPU),
+      "accelerator" => Selector::Type(DeviceType::ACCELERATOR),
+      _ => match s.parse::<usize>() {
+        Ok(index) => Selector::Index(index),
+        Err(_) => Selector::Name(String::from(s))
+      }
+    })
+  }
+}
+
+/// the inverse of from_str.
+impl fmt::Display for Selector {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Selector::Index(index) => write!(f, "{}", index),
+      Selector::Name(name) => write!(f, "{}", name),
+      Selector::Type(device_type) => write!(f, "{}", type_name(*device_type))
+    }
+  }
+}
+
+/// what a device can do, for choosing between them.
+pub struct DeviceInfo {
+  pub device: Device,
+  pub platform_index: usize,
+  pub platform_name: String,
+  pub device_index: usize,
+  pub name: String,
+  pub vendor: String,
+  pub device_type: DeviceType,
+  pub compute_units: u32,
+  /// bytes.
+  pub global_memory: u64,
+  /// largest single buffer in bytes.
+  pub max_allocation: u64,
+  /// bytes.
+  pub local_memory: u64,
+  pub image_support: bool,
+  pub max_work_group_size: usize
+}
+
+impl DeviceInfo {
+  fn new(platform_index: usize, platform: &Platform, device_index: usize, device: &Device) -> Result<Self, Error> {
+    let info = |kind: InfoKind| device.info(kind);
+    Ok(DeviceInfo {
+      device: *device,
+      platform_index: platform_index,
+      platform_name: platform.name()?,
+      device_index: device_index,
+      name: device.name()?,
+      vendor: device.vendor()?,
+      device_type: match info(InfoKind::Type)? {DeviceInfoResult::Type(device_type) => device_type, _ => DeviceType::DEFAULT},
+      compute_units: match info(InfoKind::MaxComputeUnits)? {DeviceInfoResult::MaxComputeUnits(units) => units, _ => 0},
+      global_memory: match info(InfoKind::GlobalMemSize)? {DeviceInfoResult::GlobalMemSize(size) => size, _ => 0},
+      max_allocation: match info(InfoKind::MaxMemAllocSize)? {DeviceInfoResult::MaxMemAllocSize(size) => size, _ => 0},
+      local_memory: match info(InfoKind::LocalMemSize)? {DeviceInfoResult::LocalMemSize(size) => size, _ => 0},
+      image_support: match info(InfoKind::ImageSupport)? {DeviceInfoResult::ImageSupport(support) => support, _ => false},
+      max_work_group_size: device.max_wg_size()?
+    })
+  }
+
+  pub fn get_type_name(&self) -> &'static str {
+    type_name(self.device_type)
+  }
+
+  fn matches(&self, platform: Option<&Selector>, device: Option<&Selector>) -> bool {
+    let platform_matches = match platform {
+      None => true,
+      Some(Selector::Index(index)) => self.platform_index == *index,
+      Some(Selector::Name(name)) => contains(&self.platform_name, name),
+      Some(Selector::Type(device_type)) => self.device_type.contains(*device_type)
+    };
+    let device_matches = match device {
+      None => true,
+      Some(Selector::Index(index)) => self.device_index == *index,
+      Some(Selector::Name(name)) => contains(&self.name, name),
+      Some(Selector::Type(device_type)) => self.device_type.contains(*device_type)
+    };
+    platform_matches && device_matches
+  }
+}
+
+impl fmt::Display for DeviceInfo {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    const MEBIBYTE: u64 = 1024*1024;
+    writeln!(f, "platform {} device {}: {} ({}, {})", self.platform_index, self.device_index, self.name, self.vendor, self.get_type_name())?;
+    writeln!(f, "  platform: {}", self.platform_name)?;
+    writeln!(f, "  compute units: {}", self.compute_units)?;
+    writeln!(f, "  memory: {} MiB, largest buffer {} MiB, local {} KiB", self.global_memory/MEBIBYTE, self.max_allocation/MEBIBYTE, self.local_memory/1024)?;
+    writeln!(f, "  image support: {}", if self.image_support {"yes"} else {"no"})?;
+    write!(f, "  max work group size: {}", self.max_work_group_size)
+  }
+}
+
+fn type_name(device_type: DeviceType) -> &'static str {
+  if device_type.contains(DeviceType::GPU) {
+    "gpu"
+  } else if device_type.contains(DeviceType::CPU) {
+    "cpu"
+  } else if device_type.contains(DeviceType::ACCELERATOR) {
+    "accelerator"
+  } else {
+    "other"
+  }
+}
+
+fn contains(name: &str, part: &str) -> bool {
+  name.to_lowercase().contains(&part.to_lowercase())
+}
+
+fn all_devices() -> Result<Vec<(Platform, Device, DeviceInfo)>, Error> {
+  let mut devices = Vec::new();
+  for (platform_index, platform) in Platform::list().into_iter().enumerate() {
+    for (device_index, device) in Device::list_all(&platform)?.into_iter().enumerate() {
+      let info = DeviceInfo::new(platform_index, &platform, device_index, &device)?;
+      devices.push((platform.clone(), device, info));
+    }
+  }
+  Ok(devices)
+}
+
+/// every device of every opencl platform.
+pub fn list() -> Result<Vec<DeviceInfo>, Error> {
+  Ok(all_devices()?.into_iter().map(|(_, _, info)| info).collect())
+}
+
+/// the first device matching both selectors, in platform then device order.
+/// without any selector it is ocl's default platform and its first device.
+pub fn select(platform: Option<&Selector>, device: Option<&Selector>) -> Result<(Platform, Device), Error> {
+  if platform.is_none() && device.is_none() {
+    let platform = Platform::default();
+    let device = Device::first(platform.clone())?;
+    return Ok((platform, device));
+  }
+
+  all_devices()?.into_iter()
+    .find(|(_, _, info)| info.matches(platform, device))
+    .map(|(platform, device, _)| (platform, device))
+    .ok_or_else(|| Error::NoDevice(format!("nothing matches platform {} device {}",
+      platform.map_or(String::from("any"), |selector| selector.to_string()),
+      device.map_or(String::from("any"), |selector| selector.to_string()))))
+}
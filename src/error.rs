@@ -0,0 +1,54 @@
+extern crate ocl;
+extern crate png;
+
+use std::fmt;
+
+/// errors of everything outside the kernel, rendering itself only fails with ocl errors.
+pub enum Error {
+  Ocl(ocl::Error),
+  Io(std::io::Error),
+  Png(png::EncodingError),
+  /// a malformed input file, with a description of what was wrong.
+  Parse(String),
+  /// no opencl platform or device matched the selection.
+  NoDevice(String),
+  /// the viewer window could not be opened or drawn to.
+  Window(String)
+}
+
+impl From<ocl::Error> for Error {
+  fn from(error: ocl::Error) -> Self {
+    Error::Ocl(error)
+  }
+}
+
+impl From<std::io::Error> for Error {
+  fn from(error: std::io::Error) -> Self {
+    Error::Io(error)
+  }
+}
+
+impl From<png::EncodingError> for Error {
+  fn from(error: png::EncodingError) -> Self {
+    Error::Png(error)
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::Ocl(error) => write!(f, "opencl error: {}", error),
+      Error::Io(error) => write!(f, "io error: {}", error),
+      Error::Png(error) => write!(f, "png error: {}", error),
+      Error::Parse(message) => write!(f, "parse error: {}", message),
+      Error::NoDevice(message) => write!(f, "no opencl device: {}", message),
+      Error::Window(message) => write!(f, "window error: {}", message)
+    }
+  }
+}
+
+impl fmt::Debug for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    fmt::Display::fmt(self, f)
+  }
+}
@@ -0,0 +1,27 @@
+extern crate ocl;
+
+use ocl::prm::Float8;
+
+pub struct Fog {
+  color: (u8, u8, u8),
+  density: f32,
+  height: f32,
+  height_density: f32,
+  height_falloff: f32
+}
+
+impl Fog {
+  /// density is the exponential distance fog coefficient per unit of march distance.
+  /// height_density is the extra fog density at `height`, it falls off exponentially
+  /// above it at a rate of height_falloff.
+  pub fn new(color: (u8, u8, u8), density: f32, height: f32, height_density: f32, height_falloff: f32) -> Self {
+    Fog {color: color, density: density, height: height, height_density: height_density, height_falloff: height_falloff}
+  }
+  pub fn none() -> Self {
+    Fog::new((0, 0, 0), 0., 0., 0., 0.)
+  }
+  pub fn get_data(&self) -> Float8 {
+    Float8::new(self.color.0 as f32, self.color.1 as f32, self.color.2 as f32,
+      self.density, self.height, self.height_density, self.height_falloff, 0.)
+  }
+}
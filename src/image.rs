@@ -0,0 +1,65 @@
+extern crate png;
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::error::Error;
+
+/// writes packed 0x00RRGGBB pixels, row by row from the top, as an 8 bit rgb png.
+pub fn save_png<P: AsRef<Path>>(path: P, pixels: &[u32], width: u32, height: u32) -> Result<(), Error> {
+  let writer = BufWriter::new(File::create(path)?);
+
+  let mut encoder = png::Encoder::new(writer, width, height);
+  encoder.set_color(png::ColorType::RGB);
+  encoder.set_depth(png::BitDepth::Eight);
+
+  let mut data = Vec::with_capacity(pixels.len()*3);
+  for pixel in pixels {
+    data.push((pixel >> 16) as u8);
+    data.push((pixel >> 8) as u8);
+    data.push(*pixel as u8);
+  }
+
+  encoder.write_header()?.write_image_data(&data)?;
+  Ok(())
+}
+
+/// writes samples in [0, 1] with 1 or 3 channels per pixel, row by row from the
+/// top, as a 16 bit grayscale or rgb png. samples outside [0, 1] are clamped.
+pub fn save_png16<P: AsRef<Path>>(path: P, samples: &[f32], channels: usize, width: u32, height: u32) -> Result<(), Error> {
+  let writer = BufWriter::new(File::create(path)?);
+
+  let mut encoder = png::Encoder::new(writer, width, height);
+  encoder.set_color(if channels == 1 {png::ColorType::Grayscale} else {png::ColorType::RGB});
+  encoder.set_depth(png::BitDepth::Sixteen);
+
+  // png samples are big endian.
+  let mut data = Vec::with_capacity(samples.len()*2);
+  for sample in samples {
+    let sample = (sample.max(0.).min(1.)*65535.).round() as u16;
+    data.extend_from_slice(&sample.to_be_bytes());
+  }
+
+  encoder.write_header()?.write_image_data(&data)?;
+  Ok(())
+}
+
+/// writes samples with 1 or 3 channels per pixel, row by row from the top, as a
+/// little endian pfm. pfm stores the rows from the bottom.
+pub fn save_pfm<P: AsRef<Path>>(path: P, samples: &[f32], channels: usize, width: u32, height: u32) -> Result<(), Error> {
+  let mut writer = BufWriter::new(File::create(path)?);
+
+  // a negative scale marks the samples as little endian.
+  let kind = if channels == 1 {"Pf"} else {"PF"};
+  write!(writer, "{}\n{} {}\n-1.0\n", kind, width, height)?;
+
+  let row_len = width as usize*channels;
+  for row in samples.chunks(row_len).rev() {
+    for sample in row {
+      writer.write_all(&sample.to_le_bytes())?;
+    }
+  }
+  writer.flush()?;
+  Ok(())
+}
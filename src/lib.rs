@@ -0,0 +1,25 @@
+//! OpenCL sphere tracer for scenes of signed distance field objects.
+//!
+//! A [`scene::Scene`] holds the objects and fog, a [`camera::Camera`] says where
+//! they are seen from and a [`renderer::Renderer`] runs the kernels that turn
+//! both into packed `0x00RRGGBB` pixels.
+
+pub mod math;
+pub mod scene;
+pub mod bvh;
+pub mod tile_culling;
+pub mod scene_objects;
+pub mod fog;
+pub mod camera;
+pub mod projection;
+pub mod camera_path;
+pub mod render_settings;
+pub mod progressive;
+pub mod stereo;
+pub mod renderer;
+pub mod devices;
+pub mod config;
+pub mod scene_file;
+pub mod aov;
+pub mod image;
+pub mod error;
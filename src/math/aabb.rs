@@ -0,0 +1,90 @@
+use super::vec3::Vec3;
+
+/// axis aligned box between min and max. EMPTY has min above max so any union
+/// with it is the other box.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Aabb {
+  pub min: Vec3,
+  pub max: Vec3
+}
+
+impl Aabb {
+  pub const EMPTY: Aabb = Aabb {min: Vec3::splat(std::f32::INFINITY), max: Vec3::splat(std::f32::NEG_INFINITY)};
+
+  pub fn new(min: Vec3, max: Vec3) -> Self {
+    Aabb {min: min, max: max}
+  }
+  pub fn from_center_half_extents(center: Vec3, half_extents: Vec3) -> Self {
+    Aabb {min: center - half_extents, max: center + half_extents}
+  }
+  pub fn is_empty(&self) -> bool {
+    self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+  }
+  pub fn union(&self, b: &Aabb) -> Aabb {
+    Aabb {min: self.min.min(b.min), max: self.max.max(b.max)}
+  }
+  pub fn grow(&self, point: Vec3) -> Aabb {
+    Aabb {min: self.min.min(point), max: self.max.max(point)}
+  }
+  pub fn center(&self) -> Vec3 {
+    (self.min + self.max)*0.5
+  }
+  pub fn size(&self) -> Vec3 {
+    self.max - self.min
+  }
+  /// 0 for x, 1 for y and 2 for z.
+  pub fn longest_axis(&self) -> usize {
+    let size = self.size();
+    if size.x >= size.y && size.x >= size.z {
+      0
+    } else if size.y >= size.z {
+      1
+    } else {
+      2
+    }
+  }
+  pub fn contains(&self, point: Vec3) -> bool {
+    point.x >= self.min.x && point.y >= self.min.y && point.z >= self.min.z
+      && point.x <= self.max.x && point.y <= self.max.y && point.z <= self.max.z
+  }
+  /// distance from point to the box, 0 inside it. never more than the distance to
+  /// anything inside the box, the same as the kernel's.
+  pub fn distance(&self, point: Vec3) -> f32 {
+    (self.min - point).max(point - self.max).max(Vec3::ZERO).length()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const EPSILON: f32 = 1e-5;
+
+  #[test]
+  fn union_and_grow() {
+    let a = Aabb::new(Vec3::ZERO, Vec3::ONE);
+    let b = Aabb::new(Vec3::splat(-1.), Vec3::new(0.5, 0.5, 3.));
+    assert_eq!(a.union(&b), Aabb::new(Vec3::splat(-1.), Vec3::new(1., 1., 3.)));
+    assert_eq!(Aabb::EMPTY.union(&a), a);
+    assert!(Aabb::EMPTY.is_empty());
+    assert_eq!(Aabb::EMPTY.grow(Vec3::ONE), Aabb::new(Vec3::ONE, Vec3::ONE));
+  }
+
+  #[test]
+  fn center_size_and_axis() {
+    let a = Aabb::from_center_half_extents(Vec3::new(1., 2., 3.), Vec3::new(1., 4., 2.));
+    assert!(a.center().abs_diff_eq(Vec3::new(1., 2., 3.), EPSILON));
+    assert!(a.size().abs_diff_eq(Vec3::new(2., 8., 4.), EPSILON));
+    assert_eq!(a.longest_axis(), 1);
+  }
+
+  #[test]
+  fn distance() {
+    let a = Aabb::new(Vec3::ZERO, Vec3::ONE);
+    assert_eq!(a.distance(Vec3::splat(0.5)), 0.);
+    assert!((a.distance(Vec3::new(3., 0.5, 0.5)) - 2.).abs() < EPSILON);
+    assert!((a.distance(Vec3::new(2., 2., 1.)) - 2f32.sqrt()).abs() < EPSILON);
+    assert!(a.contains(Vec3::splat(0.5)));
+    assert!(!a.contains(Vec3::splat(1.5)));
+  }
+}
@@ -0,0 +1,155 @@
+use std::ops::{Add, Sub, Mul};
+
+use super::vec3::Vec3;
+use super::quat::Quat;
+
+/// column major, each axis is where the matrix sends that basis vector.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mat3 {
+  pub x_axis: Vec3,
+  pub y_axis: Vec3,
+  pub z_axis: Vec3
+}
+
+impl Mat3 {
+  pub const ZERO: Mat3 = Mat3 {x_axis: Vec3::ZERO, y_axis: Vec3::ZERO, z_axis: Vec3::ZERO};
+  pub const IDENTITY: Mat3 = Mat3 {x_axis: Vec3::X, y_axis: Vec3::Y, z_axis: Vec3::Z};
+
+  pub const fn from_cols(x_axis: Vec3, y_axis: Vec3, z_axis: Vec3) -> Self {
+    Mat3 {x_axis: x_axis, y_axis: y_axis, z_axis: z_axis}
+  }
+  pub fn from_scale(scale: Vec3) -> Self {
+    Mat3::from_cols(Vec3::X*scale.x, Vec3::Y*scale.y, Vec3::Z*scale.z)
+  }
+  /// q must be normalized.
+  pub fn from_quat(q: Quat) -> Self {
+    Mat3::from_cols(q*Vec3::X, q*Vec3::Y, q*Vec3::Z)
+  }
+  pub fn row(&self, i: usize) -> Vec3 {
+    match i {
+      0 => Vec3::new(self.x_axis.x, self.y_axis.x, self.z_axis.x),
+      1 => Vec3::new(self.x_axis.y, self.y_axis.y, self.z_axis.y),
+      2 => Vec3::new(self.x_axis.z, self.y_axis.z, self.z_axis.z),
+      _ => panic!("Mat3 has no row {}.", i)
+    }
+  }
+  pub fn transpose(&self) -> Self {
+    Mat3::from_cols(self.row(0), self.row(1), self.row(2))
+  }
+  pub fn determinant(&self) -> f32 {
+    self.x_axis.dot(self.y_axis.cross(self.z_axis))
+  }
+  /// None if the matrix is singular.
+  pub fn inverse(&self) -> Option<Self> {
+    let det = self.determinant();
+    if det == 0. {
+      return None;
+    }
+    // the rows of the inverse are the cross products of the columns.
+    let rows = Mat3::from_cols(self.y_axis.cross(self.z_axis),
+                               self.z_axis.cross(self.x_axis),
+                               self.x_axis.cross(self.y_axis));
+    Some(rows.transpose()*(1./det))
+  }
+  pub fn abs_diff_eq(&self, b: &Mat3, max_abs_diff: f32) -> bool {
+    self.x_axis.abs_diff_eq(b.x_axis, max_abs_diff) &&
+      self.y_axis.abs_diff_eq(b.y_axis, max_abs_diff) &&
+      self.z_axis.abs_diff_eq(b.z_axis, max_abs_diff)
+  }
+}
+
+impl Add for Mat3 {
+  type Output = Mat3;
+  fn add(self, b: Mat3) -> Mat3 {
+    Mat3::from_cols(self.x_axis + b.x_axis, self.y_axis + b.y_axis, self.z_axis + b.z_axis)
+  }
+}
+
+impl Sub for Mat3 {
+  type Output = Mat3;
+  fn sub(self, b: Mat3) -> Mat3 {
+    Mat3::from_cols(self.x_axis - b.x_axis, self.y_axis - b.y_axis, self.z_axis - b.z_axis)
+  }
+}
+
+impl Mul<f32> for Mat3 {
+  type Output = Mat3;
+  fn mul(self, scale: f32) -> Mat3 {
+    Mat3::from_cols(self.x_axis*scale, self.y_axis*scale, self.z_axis*scale)
+  }
+}
+
+impl Mul<Vec3> for Mat3 {
+  type Output = Vec3;
+  fn mul(self, v: Vec3) -> Vec3 {
+    self.x_axis*v.x + self.y_axis*v.y + self.z_axis*v.z
+  }
+}
+
+/// applies b then self.
+impl Mul for Mat3 {
+  type Output = Mat3;
+  fn mul(self, b: Mat3) -> Mat3 {
+    Mat3::from_cols(self*b.x_axis, self*b.y_axis, self*b.z_axis)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::consts::FRAC_PI_2;
+
+  const EPSILON: f32 = 1e-5;
+
+  fn example() -> Mat3 {
+    Mat3::from_cols(Vec3::new(2., 0., 1.), Vec3::new(1., 3., 0.), Vec3::new(0., 1., 4.))
+  }
+
+  #[test]
+  fn multiplies_vectors() {
+    assert_eq!(Mat3::IDENTITY*Vec3::new(1., 2., 3.), Vec3::new(1., 2., 3.));
+    assert_eq!(example()*Vec3::new(1., 1., 1.), Vec3::new(3., 4., 5.));
+    assert_eq!(Mat3::from_scale(Vec3::new(2., 3., 4.))*Vec3::ONE, Vec3::new(2., 3., 4.));
+  }
+
+  #[test]
+  fn multiplies_matrices() {
+    let a = example();
+    let b = Mat3::from_quat(Quat::from_axis_angle(Vec3::Y, FRAC_PI_2));
+    let v = Vec3::new(1., -2., 3.);
+    assert!(((a*b)*v).abs_diff_eq(a*(b*v), EPSILON));
+    assert_eq!(a*Mat3::IDENTITY, a);
+  }
+
+  #[test]
+  fn addition_and_scaling() {
+    let a = example();
+    assert_eq!(a + a, a*2.);
+    assert_eq!(a - a, Mat3::ZERO);
+  }
+
+  #[test]
+  fn from_quat_matches_rotation() {
+    let q = Quat::from_euler(Vec3::new(0.3, -0.7, 1.1));
+    let v = Vec3::new(1., 2., 3.);
+    assert!((Mat3::from_quat(q)*v).abs_diff_eq(q*v, EPSILON));
+  }
+
+  #[test]
+  fn rows_and_transpose() {
+    let a = example();
+    assert_eq!(a.row(0), Vec3::new(2., 1., 0.));
+    assert_eq!(a.transpose().x_axis, a.row(0));
+    assert_eq!(a.transpose().transpose(), a);
+  }
+
+  #[test]
+  fn determinant_and_inverse() {
+    let a = example();
+    assert!((a.determinant() - 25.).abs() < EPSILON);
+    assert!((a*a.inverse().unwrap()).abs_diff_eq(&Mat3::IDENTITY, EPSILON));
+    assert!((a.inverse().unwrap()*a).abs_diff_eq(&Mat3::IDENTITY, EPSILON));
+    assert_eq!(Mat3::ZERO.inverse(), None);
+  }
+}
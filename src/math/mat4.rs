@@ -0,0 +1,235 @@
+extern crate ocl;
+
+use std::ops::Mul;
+
+use ocl::prm::Float16;
+
+use super::vec3::Vec3;
+use super::mat3::Mat3;
+use super::quat::Quat;
+
+/// column major affine or projective transform, cols[3] holds the translation.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mat4 {
+  pub cols: [[f32; 4]; 4]
+}
+
+impl Mat4 {
+  pub const IDENTITY: Mat4 = Mat4 {cols: [[1., 0., 0., 0.], [0., 1., 0., 0.], [0., 0., 1., 0.], [0., 0., 0., 1.]]};
+
+  pub const fn from_cols(cols: [[f32; 4]; 4]) -> Self {
+    Mat4 {cols: cols}
+  }
+  pub fn from_translation(translation: Vec3) -> Self {
+    let mut m = Mat4::IDENTITY;
+    m.cols[3] = [translation.x, translation.y, translation.z, 1.];
+    m
+  }
+  pub fn from_mat3(m: Mat3) -> Self {
+    Mat4::from_cols([[m.x_axis.x, m.x_axis.y, m.x_axis.z, 0.],
+                     [m.y_axis.x, m.y_axis.y, m.y_axis.z, 0.],
+                     [m.z_axis.x, m.z_axis.y, m.z_axis.z, 0.],
+                     [0., 0., 0., 1.]])
+  }
+  pub fn from_scale(scale: Vec3) -> Self {
+    Mat4::from_mat3(Mat3::from_scale(scale))
+  }
+  pub fn from_quat(q: Quat) -> Self {
+    Mat4::from_mat3(Mat3::from_quat(q))
+  }
+  /// scales, then rotates, then translates.
+  pub fn from_scale_rotation_translation(scale: Vec3, rotation: Quat, translation: Vec3) -> Self {
+    let mut m = Mat4::from_mat3(Mat3::from_quat(rotation)*Mat3::from_scale(scale));
+    m.cols[3] = [translation.x, translation.y, translation.z, 1.];
+    m
+  }
+  /// upper left 3x3, the linear part of an affine transform.
+  pub fn get_mat3(&self) -> Mat3 {
+    let c = &self.cols;
+    Mat3::from_cols(Vec3::new(c[0][0], c[0][1], c[0][2]),
+                    Vec3::new(c[1][0], c[1][1], c[1][2]),
+                    Vec3::new(c[2][0], c[2][1], c[2][2]))
+  }
+  pub fn get_translation(&self) -> Vec3 {
+    Vec3::new(self.cols[3][0], self.cols[3][1], self.cols[3][2])
+  }
+  pub fn transpose(&self) -> Self {
+    let mut m = Mat4::IDENTITY;
+    for col in 0..4 {
+      for row in 0..4 {
+        m.cols[col][row] = self.cols[row][col];
+      }
+    }
+    m
+  }
+  /// gauss jordan elimination with partial pivoting, None if the matrix is singular.
+  pub fn inverse(&self) -> Option<Self> {
+    // rows of [self | identity].
+    let mut a = [[0f32; 8]; 4];
+    for row in 0..4 {
+      for col in 0..4 {
+        a[row][col] = self.cols[col][row];
+      }
+      a[row][4 + row] = 1.;
+    }
+
+    for col in 0..4 {
+      let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().partial_cmp(&a[j][col].abs()).unwrap()).unwrap();
+      if a[pivot][col] == 0. {
+        return None;
+      }
+      a.swap(col, pivot);
+      let scale = 1./a[col][col];
+      for x in a[col].iter_mut() {
+        *x *= scale;
+      }
+      for row in 0..4 {
+        if row != col {
+          let factor = a[row][col];
+          for k in 0..8 {
+            a[row][k] -= factor*a[col][k];
+          }
+        }
+      }
+    }
+
+    let mut m = Mat4::IDENTITY;
+    for row in 0..4 {
+      for col in 0..4 {
+        m.cols[col][row] = a[row][4 + col];
+      }
+    }
+    Some(m)
+  }
+  /// treats point as having w of 1, with the perspective divide.
+  pub fn transform_point(&self, point: Vec3) -> Vec3 {
+    let c = &self.cols;
+    let mut out = [0.; 4];
+    for row in 0..4 {
+      out[row] = c[0][row]*point.x + c[1][row]*point.y + c[2][row]*point.z + c[3][row];
+    }
+    Vec3::new(out[0], out[1], out[2])/out[3]
+  }
+  /// treats v as having w of 0, so translation is ignored.
+  pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+    self.get_mat3()*v
+  }
+  pub fn abs_diff_eq(&self, b: &Mat4, max_abs_diff: f32) -> bool {
+    self.cols.iter().flatten().zip(b.cols.iter().flatten()).all(|(x, y)| (x - y).abs() <= max_abs_diff)
+  }
+}
+
+/// applies b then self.
+impl Mul for Mat4 {
+  type Output = Mat4;
+  fn mul(self, b: Mat4) -> Mat4 {
+    let mut m = Mat4::from_cols([[0.; 4]; 4]);
+    for col in 0..4 {
+      for row in 0..4 {
+        m.cols[col][row] = (0..4).map(|k| self.cols[k][row]*b.cols[col][k]).sum();
+      }
+    }
+    m
+  }
+}
+
+/// column major, the same order as an opencl float16 read as four float4 columns.
+impl From<Mat4> for Float16 {
+  fn from(m: Mat4) -> Self {
+    let c = &m.cols;
+    Float16::new(c[0][0], c[0][1], c[0][2], c[0][3],
+                 c[1][0], c[1][1], c[1][2], c[1][3],
+                 c[2][0], c[2][1], c[2][2], c[2][3],
+                 c[3][0], c[3][1], c[3][2], c[3][3])
+  }
+}
+
+impl From<Float16> for Mat4 {
+  fn from(data: Float16) -> Self {
+    let mut m = Mat4::IDENTITY;
+    for i in 0..16 {
+      m.cols[i/4][i%4] = data[i];
+    }
+    m
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const EPSILON: f32 = 1e-5;
+
+  fn example() -> Mat4 {
+    Mat4::from_scale_rotation_translation(Vec3::new(2., 3., 0.5), Quat::from_euler(Vec3::new(0.3, -0.7, 1.1)), Vec3::new(1., -2., 5.))
+  }
+
+  #[test]
+  fn translation() {
+    let m = Mat4::from_translation(Vec3::new(1., 2., 3.));
+    assert_eq!(m.transform_point(Vec3::ONE), Vec3::new(2., 3., 4.));
+    assert_eq!(m.transform_vector(Vec3::ONE), Vec3::ONE);
+    assert_eq!(m.get_translation(), Vec3::new(1., 2., 3.));
+  }
+
+  #[test]
+  fn scale_and_rotation() {
+    assert_eq!(Mat4::from_scale(Vec3::new(2., 3., 4.)).transform_point(Vec3::ONE), Vec3::new(2., 3., 4.));
+    let q = Quat::from_euler(Vec3::new(0.3, -0.7, 1.1));
+    assert!(Mat4::from_quat(q).transform_point(Vec3::new(1., 2., 3.)).abs_diff_eq(q*Vec3::new(1., 2., 3.), EPSILON));
+  }
+
+  #[test]
+  fn scale_rotation_translation_order() {
+    let q = Quat::from_euler(Vec3::new(0.3, -0.7, 1.1));
+    let expected = q*(Vec3::new(1., 2., 3.)*Vec3::new(2., 3., 0.5)) + Vec3::new(1., -2., 5.);
+    assert!(example().transform_point(Vec3::new(1., 2., 3.)).abs_diff_eq(expected, EPSILON));
+  }
+
+  #[test]
+  fn mat3_round_trip() {
+    let m = Mat3::from_quat(Quat::from_euler(Vec3::new(0.3, -0.7, 1.1)));
+    assert_eq!(Mat4::from_mat3(m).get_mat3(), m);
+  }
+
+  #[test]
+  fn multiplication_composes() {
+    let a = example();
+    let b = Mat4::from_translation(Vec3::new(-3., 4., 1.));
+    let v = Vec3::new(1., 2., 3.);
+    assert!((a*b).transform_point(v).abs_diff_eq(a.transform_point(b.transform_point(v)), EPSILON));
+    assert_eq!(a*Mat4::IDENTITY, a);
+  }
+
+  #[test]
+  fn transpose() {
+    let a = example();
+    assert_eq!(a.transpose().cols[0][3], a.cols[3][0]);
+    assert_eq!(a.transpose().transpose(), a);
+  }
+
+  #[test]
+  fn inverse() {
+    let a = example();
+    assert!((a*a.inverse().unwrap()).abs_diff_eq(&Mat4::IDENTITY, EPSILON));
+    assert!((a.inverse().unwrap()*a).abs_diff_eq(&Mat4::IDENTITY, EPSILON));
+    assert_eq!(Mat4::from_scale(Vec3::new(1., 0., 1.)).inverse(), None);
+  }
+
+  #[test]
+  fn perspective_divide() {
+    let mut m = Mat4::IDENTITY;
+    m.cols[3][3] = 2.;
+    assert_eq!(m.transform_point(Vec3::new(2., 4., 6.)), Vec3::new(1., 2., 3.));
+  }
+
+  #[test]
+  fn conversions() {
+    let a = example();
+    let data = Float16::from(a);
+    assert_eq!(data[12], 1.);
+    assert_eq!(data[3], 0.);
+    assert_eq!(Mat4::from(data), a);
+  }
+}
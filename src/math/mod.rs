@@ -0,0 +1,13 @@
+pub mod vec3;
+pub mod mat3;
+pub mod mat4;
+pub mod quat;
+pub mod transform;
+pub mod aabb;
+
+pub use vec3::Vec3;
+pub use mat3::Mat3;
+pub use mat4::Mat4;
+pub use quat::Quat;
+pub use transform::Transform;
+pub use aabb::Aabb;
@@ -0,0 +1,217 @@
+extern crate ocl;
+
+use std::ops::Mul;
+
+use ocl::prm::Float4;
+
+use super::vec3::Vec3;
+use super::mat3::Mat3;
+
+/// unit quaternions are rotations, x y z is the axis part and w the angle part.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Quat {
+  pub x: f32,
+  pub y: f32,
+  pub z: f32,
+  pub w: f32
+}
+
+impl Quat {
+  pub const IDENTITY: Quat = Quat {x: 0., y: 0., z: 0., w: 1.};
+
+  pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+    Quat {x: x, y: y, z: z, w: w}
+  }
+  /// axis must be normalized.
+  pub fn from_axis_angle(axis: Vec3, rad: f32) -> Self {
+    let half_sin = (rad/2.).sin();
+    Quat::new(axis.x*half_sin, axis.y*half_sin, axis.z*half_sin, (rad/2.).cos())
+  }
+  /// same convention as vecRotate in the kernel, rotate around x then y then z.
+  pub fn from_euler(rotation: Vec3) -> Self {
+    let qx = Quat::from_axis_angle(Vec3::X, rotation.x);
+    let qy = Quat::from_axis_angle(Vec3::Y, rotation.y);
+    let qz = Quat::from_axis_angle(Vec3::Z, rotation.z);
+    qz*qy*qx
+  }
+  /// the rotation of a matrix with orthonormal columns.
+  pub fn from_mat3(m: &Mat3) -> Self {
+    let (a, b, c) = (m.x_axis, m.y_axis, m.z_axis);
+    let trace = a.x + b.y + c.z;
+    if trace > 0. {
+      let s = (trace + 1.).sqrt()*2.;
+      Quat::new((b.z - c.y)/s, (c.x - a.z)/s, (a.y - b.x)/s, 0.25*s)
+    } else if a.x > b.y && a.x > c.z {
+      let s = (1. + a.x - b.y - c.z).sqrt()*2.;
+      Quat::new(0.25*s, (b.x + a.y)/s, (c.x + a.z)/s, (b.z - c.y)/s)
+    } else if b.y > c.z {
+      let s = (1. + b.y - a.x - c.z).sqrt()*2.;
+      Quat::new((b.x + a.y)/s, 0.25*s, (c.y + b.z)/s, (c.x - a.z)/s)
+    } else {
+      let s = (1. + c.z - a.x - b.y).sqrt()*2.;
+      Quat::new((c.x + a.z)/s, (c.y + b.z)/s, 0.25*s, (a.y - b.x)/s)
+    }.normalize()
+  }
+  pub fn dot(self, b: Quat) -> f32 {
+    self.x*b.x + self.y*b.y + self.z*b.z + self.w*b.w
+  }
+  pub fn length(self) -> f32 {
+    self.dot(self).sqrt()
+  }
+  pub fn normalize(self) -> Self {
+    let len = self.length();
+    Quat::new(self.x/len, self.y/len, self.z/len, self.w/len)
+  }
+  pub fn conjugate(self) -> Self {
+    Quat::new(-self.x, -self.y, -self.z, self.w)
+  }
+  /// the opposite rotation, the same as the conjugate for unit quaternions.
+  pub fn inverse(self) -> Self {
+    let len_squared = self.dot(self);
+    let c = self.conjugate();
+    Quat::new(c.x/len_squared, c.y/len_squared, c.z/len_squared, c.w/len_squared)
+  }
+  /// spherical interpolation along the shorter arc, t of 0 is self and 1 is b.
+  pub fn slerp(self, b: Quat, t: f32) -> Self {
+    let mut cos_angle = self.dot(b);
+    let b = if cos_angle < 0. {
+      cos_angle = -cos_angle;
+      Quat::new(-b.x, -b.y, -b.z, -b.w)
+    } else {b};
+
+    //nearly parallel, lerp avoids dividing by a tiny sin.
+    let (scale_a, scale_b) = if cos_angle > 0.9995 {
+      (1. - t, t)
+    } else {
+      let angle = cos_angle.acos();
+      let sin_angle = angle.sin();
+      (((1. - t)*angle).sin()/sin_angle, (t*angle).sin()/sin_angle)
+    };
+
+    Quat::new(self.x*scale_a + b.x*scale_b,
+              self.y*scale_a + b.y*scale_b,
+              self.z*scale_a + b.z*scale_b,
+              self.w*scale_a + b.w*scale_b).normalize()
+  }
+  pub fn abs_diff_eq(self, b: Quat, max_abs_diff: f32) -> bool {
+    (self.x - b.x).abs() <= max_abs_diff && (self.y - b.y).abs() <= max_abs_diff &&
+      (self.z - b.z).abs() <= max_abs_diff && (self.w - b.w).abs() <= max_abs_diff
+  }
+}
+
+/// rotating by the result is rotating by b then by self.
+impl Mul for Quat {
+  type Output = Quat;
+  fn mul(self, b: Quat) -> Quat {
+    Quat::new(self.w*b.x + self.x*b.w + self.y*b.z - self.z*b.y,
+              self.w*b.y - self.x*b.z + self.y*b.w + self.z*b.x,
+              self.w*b.z + self.x*b.y - self.y*b.x + self.z*b.w,
+              self.w*b.w - self.x*b.x - self.y*b.y - self.z*b.z)
+  }
+}
+
+/// rotates v, self must be normalized.
+impl Mul<Vec3> for Quat {
+  type Output = Vec3;
+  fn mul(self, v: Vec3) -> Vec3 {
+    let axis = Vec3::new(self.x, self.y, self.z);
+    let t = axis.cross(v)*2.;
+    v + t*self.w + axis.cross(t)
+  }
+}
+
+impl From<Quat> for Float4 {
+  fn from(q: Quat) -> Self {
+    Float4::new(q.x, q.y, q.z, q.w)
+  }
+}
+
+impl From<Float4> for Quat {
+  fn from(q: Float4) -> Self {
+    Quat::new(q[0], q[1], q[2], q[3])
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+  const EPSILON: f32 = 1e-5;
+
+  #[test]
+  fn axis_angle_rotates_vectors() {
+    let q = Quat::from_axis_angle(Vec3::Y, FRAC_PI_2);
+    assert!((q*Vec3::Z).abs_diff_eq(Vec3::X, EPSILON));
+    assert!((q*Vec3::X).abs_diff_eq(-Vec3::Z, EPSILON));
+    assert!((Quat::IDENTITY*Vec3::new(1., 2., 3.)).abs_diff_eq(Vec3::new(1., 2., 3.), EPSILON));
+  }
+
+  #[test]
+  fn multiplication_composes_rotations() {
+    let a = Quat::from_axis_angle(Vec3::X, FRAC_PI_4);
+    let b = Quat::from_axis_angle(Vec3::Y, FRAC_PI_2);
+    let v = Vec3::new(1., 2., 3.);
+    assert!(((a*b)*v).abs_diff_eq(a*(b*v), EPSILON));
+    assert!((a*Quat::IDENTITY).abs_diff_eq(a, EPSILON));
+  }
+
+  #[test]
+  fn euler_rotates_x_then_y_then_z() {
+    let rotation = Vec3::new(0.3, -0.7, 1.1);
+    let expected = Quat::from_axis_angle(Vec3::Z, rotation.z)*(Quat::from_axis_angle(Vec3::Y, rotation.y)*(Quat::from_axis_angle(Vec3::X, rotation.x)*Vec3::ONE));
+    assert!((Quat::from_euler(rotation)*Vec3::ONE).abs_diff_eq(expected, EPSILON));
+  }
+
+  #[test]
+  fn mat3_round_trip() {
+    for q in &[Quat::IDENTITY,
+               Quat::from_axis_angle(Vec3::X, PI),
+               Quat::from_axis_angle(Vec3::Y, PI),
+               Quat::from_axis_angle(Vec3::Z, PI),
+               Quat::from_euler(Vec3::new(0.3, -0.7, 1.1))] {
+      let back = Quat::from_mat3(&Mat3::from_quat(*q));
+      // q and -q are the same rotation.
+      assert!(back.abs_diff_eq(*q, EPSILON) || back.abs_diff_eq(Quat::new(-q.x, -q.y, -q.z, -q.w), EPSILON));
+    }
+  }
+
+  #[test]
+  fn length_and_normalize() {
+    let q = Quat::new(1., 2., 2., 4.);
+    assert_eq!(q.dot(q), 25.);
+    assert_eq!(q.length(), 5.);
+    assert!(q.normalize().abs_diff_eq(Quat::new(0.2, 0.4, 0.4, 0.8), EPSILON));
+  }
+
+  #[test]
+  fn conjugate_and_inverse_undo_the_rotation() {
+    let q = Quat::from_euler(Vec3::new(0.3, -0.7, 1.1));
+    assert_eq!(q.conjugate(), Quat::new(-q.x, -q.y, -q.z, q.w));
+    assert!((q*q.inverse()).abs_diff_eq(Quat::IDENTITY, EPSILON));
+    assert!((q.conjugate()*(q*Vec3::X)).abs_diff_eq(Vec3::X, EPSILON));
+    let scaled = Quat::new(q.x*2., q.y*2., q.z*2., q.w*2.);
+    assert!((scaled*scaled.inverse()).abs_diff_eq(Quat::IDENTITY, EPSILON));
+  }
+
+  #[test]
+  fn slerp_interpolates_the_angle() {
+    let a = Quat::IDENTITY;
+    let b = Quat::from_axis_angle(Vec3::Y, FRAC_PI_2);
+    assert!(a.slerp(b, 0.).abs_diff_eq(a, EPSILON));
+    assert!(a.slerp(b, 1.).abs_diff_eq(b, EPSILON));
+    assert!(a.slerp(b, 0.5).abs_diff_eq(Quat::from_axis_angle(Vec3::Y, FRAC_PI_4), EPSILON));
+    // takes the shorter arc when the signs differ.
+    let negative_b = Quat::new(-b.x, -b.y, -b.z, -b.w);
+    assert!((a.slerp(negative_b, 0.5)*Vec3::Z).abs_diff_eq(Quat::from_axis_angle(Vec3::Y, FRAC_PI_4)*Vec3::Z, EPSILON));
+  }
+
+  #[test]
+  fn conversions() {
+    let q = Quat::new(1., 2., 3., 4.);
+    let data = Float4::from(q);
+    assert_eq!((data[0], data[1], data[2], data[3]), (1., 2., 3., 4.));
+    assert_eq!(Quat::from(data), q);
+  }
+}
@@ -0,0 +1,109 @@
+use std::ops::Mul;
+
+use super::vec3::Vec3;
+use super::mat4::Mat4;
+use super::quat::Quat;
+
+/// scales, then rotates, then translates. cheaper to compose and invert than a Mat4
+/// and keeps the rotation as a quaternion.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Transform {
+  pub translation: Vec3,
+  pub rotation: Quat,
+  pub scale: Vec3
+}
+
+impl Transform {
+  pub const IDENTITY: Transform = Transform {translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: Vec3::ONE};
+
+  pub fn new(translation: Vec3, rotation: Quat, scale: Vec3) -> Self {
+    Transform {translation: translation, rotation: rotation, scale: scale}
+  }
+  pub fn from_translation(translation: Vec3) -> Self {
+    Transform {translation: translation, ..Transform::IDENTITY}
+  }
+  pub fn from_rotation(rotation: Quat) -> Self {
+    Transform {rotation: rotation, ..Transform::IDENTITY}
+  }
+  pub fn from_scale(scale: Vec3) -> Self {
+    Transform {scale: scale, ..Transform::IDENTITY}
+  }
+  pub fn transform_point(&self, point: Vec3) -> Vec3 {
+    self.rotation*(point*self.scale) + self.translation
+  }
+  pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+    self.rotation*(v*self.scale)
+  }
+  /// exact when the scale is uniform, a rotated non uniform scale can't be
+  /// undone by a scale followed by a rotation.
+  pub fn inverse(&self) -> Self {
+    let rotation = self.rotation.conjugate();
+    let scale = Vec3::ONE/self.scale;
+    Transform {translation: -(rotation*self.translation)*scale, rotation: rotation, scale: scale}
+  }
+  pub fn to_mat4(&self) -> Mat4 {
+    Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+  }
+}
+
+/// applies b then self, exact when self has a uniform scale.
+impl Mul for Transform {
+  type Output = Transform;
+  fn mul(self, b: Transform) -> Transform {
+    Transform {translation: self.transform_point(b.translation),
+               rotation: self.rotation*b.rotation,
+               scale: self.scale*b.scale}
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const EPSILON: f32 = 1e-5;
+
+  fn example() -> Transform {
+    Transform::new(Vec3::new(1., -2., 5.), Quat::from_euler(Vec3::new(0.3, -0.7, 1.1)), Vec3::splat(2.))
+  }
+
+  #[test]
+  fn transforms_points_and_vectors() {
+    let t = example();
+    let v = Vec3::new(1., 2., 3.);
+    assert!(t.transform_point(v).abs_diff_eq(t.rotation*(v*2.) + t.translation, EPSILON));
+    assert!(t.transform_vector(v).abs_diff_eq(t.rotation*(v*2.), EPSILON));
+    assert_eq!(Transform::IDENTITY.transform_point(v), v);
+  }
+
+  #[test]
+  fn constructors() {
+    let v = Vec3::new(1., 2., 3.);
+    assert_eq!(Transform::from_translation(Vec3::ONE).transform_point(v), Vec3::new(2., 3., 4.));
+    assert_eq!(Transform::from_scale(Vec3::splat(2.)).transform_point(v), v*2.);
+    let q = Quat::from_axis_angle(Vec3::Y, 0.5);
+    assert!(Transform::from_rotation(q).transform_point(v).abs_diff_eq(q*v, EPSILON));
+  }
+
+  #[test]
+  fn composition() {
+    let a = example();
+    let b = Transform::new(Vec3::new(-3., 4., 1.), Quat::from_axis_angle(Vec3::X, 0.4), Vec3::new(1., 2., 3.));
+    let v = Vec3::new(1., 2., 3.);
+    assert!((a*b).transform_point(v).abs_diff_eq(a.transform_point(b.transform_point(v)), 1e-4));
+  }
+
+  #[test]
+  fn inverse() {
+    let t = example();
+    let v = Vec3::new(1., 2., 3.);
+    assert!(t.inverse().transform_point(t.transform_point(v)).abs_diff_eq(v, EPSILON));
+    assert!((t*t.inverse()).transform_point(v).abs_diff_eq(v, EPSILON));
+  }
+
+  #[test]
+  fn matches_mat4() {
+    let t = example();
+    let v = Vec3::new(1., 2., 3.);
+    assert!(t.to_mat4().transform_point(v).abs_diff_eq(t.transform_point(v), EPSILON));
+  }
+}
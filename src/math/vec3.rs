@@ -0,0 +1,278 @@
+extern crate ocl;
+extern crate fast_inv_sqrt;
+
+use std::ops::{Add, Sub, Mul, Div, Neg, AddAssign, SubAssign, MulAssign, DivAssign};
+
+use fast_inv_sqrt::InvSqrt32;
+use ocl::prm::Float3;
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Vec3 {
+  pub x: f32,
+  pub y: f32,
+  pub z: f32
+}
+
+impl Vec3 {
+  pub const ZERO: Vec3 = Vec3 {x: 0., y: 0., z: 0.};
+  pub const ONE: Vec3 = Vec3 {x: 1., y: 1., z: 1.};
+  pub const X: Vec3 = Vec3 {x: 1., y: 0., z: 0.};
+  pub const Y: Vec3 = Vec3 {x: 0., y: 1., z: 0.};
+  pub const Z: Vec3 = Vec3 {x: 0., y: 0., z: 1.};
+
+  pub const fn new(x: f32, y: f32, z: f32) -> Self {
+    Vec3 {x: x, y: y, z: z}
+  }
+  pub const fn splat(v: f32) -> Self {
+    Vec3 {x: v, y: v, z: v}
+  }
+  pub fn dot(self, b: Vec3) -> f32 {
+    self.x*b.x + self.y*b.y + self.z*b.z
+  }
+  pub fn cross(self, b: Vec3) -> Vec3 {
+    Vec3::new(self.y*b.z - self.z*b.y, self.z*b.x - self.x*b.z, self.x*b.y - self.y*b.x)
+  }
+  pub fn length_squared(self) -> f32 {
+    self.dot(self)
+  }
+  pub fn length(self) -> f32 {
+    self.dot(self).sqrt()
+  }
+  pub fn fast_inv_length(self) -> f32 {
+    self.dot(self).inv_sqrt32()
+  }
+  pub fn normalize(self) -> Vec3 {
+    self/self.length()
+  }
+  /// less accurate than normalize, good enough for directions that get renormalized anyway.
+  pub fn fast_normalize(self) -> Vec3 {
+    self*self.fast_inv_length()
+  }
+  /// projection of self onto dir, dir must be normalized.
+  pub fn proj_onto(self, dir: Vec3) -> Vec3 {
+    dir*self.dot(dir)
+  }
+  /// projection of self onto the plane through the origin with normal plane_norm, plane_norm must be normalized.
+  pub fn proj_onto_plane(self, plane_norm: Vec3) -> Vec3 {
+    self - self.proj_onto(plane_norm)
+  }
+  /// t of 0 is self and 1 is b.
+  pub fn lerp(self, b: Vec3, t: f32) -> Vec3 {
+    self + (b - self)*t
+  }
+  pub fn abs(self) -> Vec3 {
+    Vec3::new(self.x.abs(), self.y.abs(), self.z.abs())
+  }
+  /// component wise minimum.
+  pub fn min(self, b: Vec3) -> Vec3 {
+    Vec3::new(self.x.min(b.x), self.y.min(b.y), self.z.min(b.z))
+  }
+  /// component wise maximum.
+  pub fn max(self, b: Vec3) -> Vec3 {
+    Vec3::new(self.x.max(b.x), self.y.max(b.y), self.z.max(b.z))
+  }
+  pub fn min_element(self) -> f32 {
+    self.x.min(self.y.min(self.z))
+  }
+  pub fn max_element(self) -> f32 {
+    self.x.max(self.y.max(self.z))
+  }
+  pub fn abs_diff_eq(self, b: Vec3, max_abs_diff: f32) -> bool {
+    (self - b).abs().max_element() <= max_abs_diff
+  }
+}
+
+impl Add for Vec3 {
+  type Output = Vec3;
+  fn add(self, b: Vec3) -> Vec3 {
+    Vec3::new(self.x + b.x, self.y + b.y, self.z + b.z)
+  }
+}
+
+impl Sub for Vec3 {
+  type Output = Vec3;
+  fn sub(self, b: Vec3) -> Vec3 {
+    Vec3::new(self.x - b.x, self.y - b.y, self.z - b.z)
+  }
+}
+
+impl Mul<f32> for Vec3 {
+  type Output = Vec3;
+  fn mul(self, scale: f32) -> Vec3 {
+    Vec3::new(self.x*scale, self.y*scale, self.z*scale)
+  }
+}
+
+impl Mul<Vec3> for f32 {
+  type Output = Vec3;
+  fn mul(self, v: Vec3) -> Vec3 {
+    v*self
+  }
+}
+
+/// component wise.
+impl Mul for Vec3 {
+  type Output = Vec3;
+  fn mul(self, b: Vec3) -> Vec3 {
+    Vec3::new(self.x*b.x, self.y*b.y, self.z*b.z)
+  }
+}
+
+impl Div<f32> for Vec3 {
+  type Output = Vec3;
+  fn div(self, scale: f32) -> Vec3 {
+    Vec3::new(self.x/scale, self.y/scale, self.z/scale)
+  }
+}
+
+/// component wise.
+impl Div for Vec3 {
+  type Output = Vec3;
+  fn div(self, b: Vec3) -> Vec3 {
+    Vec3::new(self.x/b.x, self.y/b.y, self.z/b.z)
+  }
+}
+
+impl Neg for Vec3 {
+  type Output = Vec3;
+  fn neg(self) -> Vec3 {
+    Vec3::new(-self.x, -self.y, -self.z)
+  }
+}
+
+impl AddAssign for Vec3 {
+  fn add_assign(&mut self, b: Vec3) {
+    *self = *self + b;
+  }
+}
+
+impl SubAssign for Vec3 {
+  fn sub_assign(&mut self, b: Vec3) {
+    *self = *self - b;
+  }
+}
+
+impl MulAssign<f32> for Vec3 {
+  fn mul_assign(&mut self, scale: f32) {
+    *self = *self*scale;
+  }
+}
+
+impl DivAssign<f32> for Vec3 {
+  fn div_assign(&mut self, scale: f32) {
+    *self = *self/scale;
+  }
+}
+
+impl From<(f32, f32, f32)> for Vec3 {
+  fn from(v: (f32, f32, f32)) -> Self {
+    Vec3::new(v.0, v.1, v.2)
+  }
+}
+
+impl From<Vec3> for (f32, f32, f32) {
+  fn from(v: Vec3) -> Self {
+    (v.x, v.y, v.z)
+  }
+}
+
+impl From<Vec3> for Float3 {
+  fn from(v: Vec3) -> Self {
+    Float3::new(v.x, v.y, v.z)
+  }
+}
+
+impl From<Float3> for Vec3 {
+  fn from(v: Float3) -> Self {
+    Vec3::new(v[0], v[1], v[2])
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const EPSILON: f32 = 1e-5;
+
+  #[test]
+  fn arithmetic() {
+    let a = Vec3::new(1., 2., 3.);
+    let b = Vec3::new(4., -5., 6.);
+    assert_eq!(a + b, Vec3::new(5., -3., 9.));
+    assert_eq!(a - b, Vec3::new(-3., 7., -3.));
+    assert_eq!(a*2., Vec3::new(2., 4., 6.));
+    assert_eq!(2.*a, Vec3::new(2., 4., 6.));
+    assert_eq!(a*b, Vec3::new(4., -10., 18.));
+    assert_eq!(a/2., Vec3::new(0.5, 1., 1.5));
+    assert_eq!(b/a, Vec3::new(4., -2.5, 2.));
+    assert_eq!(-a, Vec3::new(-1., -2., -3.));
+  }
+
+  #[test]
+  fn assign_operators() {
+    let mut v = Vec3::new(1., 2., 3.);
+    v += Vec3::ONE;
+    assert_eq!(v, Vec3::new(2., 3., 4.));
+    v -= Vec3::new(2., 2., 2.);
+    assert_eq!(v, Vec3::new(0., 1., 2.));
+    v *= 3.;
+    assert_eq!(v, Vec3::new(0., 3., 6.));
+    v /= 3.;
+    assert_eq!(v, Vec3::new(0., 1., 2.));
+  }
+
+  #[test]
+  fn dot_and_cross() {
+    let a = Vec3::new(1., 2., 3.);
+    let b = Vec3::new(4., -5., 6.);
+    assert_eq!(a.dot(b), 12.);
+    assert_eq!(Vec3::X.cross(Vec3::Y), Vec3::Z);
+    assert_eq!(Vec3::Y.cross(Vec3::Z), Vec3::X);
+    let c = a.cross(b);
+    assert!(c.dot(a).abs() < EPSILON);
+    assert!(c.dot(b).abs() < EPSILON);
+  }
+
+  #[test]
+  fn length_and_normalize() {
+    let v = Vec3::new(3., 0., 4.);
+    assert_eq!(v.length_squared(), 25.);
+    assert_eq!(v.length(), 5.);
+    assert!((v.fast_inv_length() - 0.2).abs() < 1e-2);
+    assert!(v.normalize().abs_diff_eq(Vec3::new(0.6, 0., 0.8), EPSILON));
+    assert!(v.fast_normalize().abs_diff_eq(Vec3::new(0.6, 0., 0.8), 1e-2));
+  }
+
+  #[test]
+  fn projections() {
+    let v = Vec3::new(1., 2., 3.);
+    assert_eq!(v.proj_onto(Vec3::Y), Vec3::new(0., 2., 0.));
+    assert_eq!(v.proj_onto_plane(Vec3::Y), Vec3::new(1., 0., 3.));
+  }
+
+  #[test]
+  fn component_wise() {
+    let a = Vec3::new(1., -2., 3.);
+    let b = Vec3::new(-1., 5., 2.);
+    assert_eq!(a.abs(), Vec3::new(1., 2., 3.));
+    assert_eq!(a.min(b), Vec3::new(-1., -2., 2.));
+    assert_eq!(a.max(b), Vec3::new(1., 5., 3.));
+    assert_eq!(a.min_element(), -2.);
+    assert_eq!(a.max_element(), 3.);
+    assert_eq!(Vec3::splat(2.), Vec3::new(2., 2., 2.));
+    assert_eq!(a.lerp(b, 0.5), Vec3::new(0., 1.5, 2.5));
+    assert!(a.abs_diff_eq(a + Vec3::splat(0.5*EPSILON), EPSILON));
+    assert!(!a.abs_diff_eq(a + Vec3::X, EPSILON));
+  }
+
+  #[test]
+  fn conversions() {
+    let v = Vec3::new(1., 2., 3.);
+    assert_eq!(Vec3::from((1., 2., 3.)), v);
+    assert_eq!(<(f32, f32, f32)>::from(v), (1., 2., 3.));
+    let data = Float3::from(v);
+    assert_eq!((data[0], data[1], data[2]), (1., 2., 3.));
+    assert_eq!(Vec3::from(data), v);
+  }
+}
@@ -1,22 +1,64 @@
-#define SMALLEST_DIST (float)0.01
-#define NORMAL_EPSILON (float)0.1
-#define MAX_ITERATIONS 100
-#define MAX_DIST 100
-#define MAX_REFLECTION_DEPTH 3
-#define MIN_REFLECTION_CUTOFF 0.05
+#define POINT_LIGHT_INTENSITY 500
+#define RUSSIAN_ROULETTE_START_BOUNCE 3
 
-#define SCALE_UCHAR3_BY_FLOAT(a, f) (uchar3)((uchar)((float)a.s0*f), (uchar)((float)a.s1*f), (uchar)((float)a.s2*f))
+#define UCHAR3_TO_LINEAR(a) srgbToLinear(convert_float3(a)/(float)255)
+
+#define TONE_MAPPING_NONE 0
+#define TONE_MAPPING_REINHARD 1
+#define TONE_MAPPING_ACES 2
+#define TONE_MAPPING_FILMIC 3
+
+#define AA_NONE 0
+#define AA_GRID 1
+#define AA_ROTATED_GRID 2
+#define AA_STRATIFIED 3
+
+#define DEBUG_SHADED 0
+#define DEBUG_NORMALS 1
+#define DEBUG_DEPTH 2
+#define DEBUG_ITERATIONS 3
+#define DEBUG_OBJECT_ID 4
+#define DEBUG_SHADOW 5
 
 #define OBJECT_TYPE(a) a.s0
 #define OBJECT_COLOR(a) a.s123
 
 #define CAMERA_POS(a) a.s012
-#define CAMERA_ROTATION(a) a.s345
-#define CAMERA_FRAME_DIST(a) a.s6
-#define CAMERA_SCALE(a) a.s7
+#define CAMERA_RIGHT(a) a.s345
+#define CAMERA_UP(a) a.s678
+#define CAMERA_FORWARD(a) cross(a.s345, a.s678)
+#define CAMERA_PROJECTION(a) (uint)a.s9
+#define CAMERA_APERTURE(a) a.sD
+#define CAMERA_FOCUS_DIST(a) a.sE
+#define CAMERA_VIEW_SHIFT(a) a.sF
+
+#define PROJECTION_LEGACY 0
+#define LEGACY_FRAME_DIST(a) a.sA
+#define LEGACY_SCALE(a) a.sB
+
+#define PROJECTION_PERSPECTIVE 1
+#define PERSPECTIVE_TAN_HALF_FOV(a) a.sA
+#define PERSPECTIVE_ASPECT(a) a.sB
+#define PERSPECTIVE_NEAR(a) a.sC
 
+#define PROJECTION_ORTHOGRAPHIC 2
+#define ORTHOGRAPHIC_HEIGHT(a) a.sA
+
+#define PROJECTION_FISHEYE 3
+#define FISHEYE_HALF_ANGLE(a) a.sA
+
+#define PROJECTION_EQUIRECTANGULAR 4
+
+#define ROUGHNESS(a) a.sD
+#define EMISSION(a) a.sE
 #define REFLECTIVITY(a) a.sF
 
+#define FOG_COLOR(a) a.s012
+#define FOG_DENSITY(a) a.s3
+#define FOG_HEIGHT(a) a.s4
+#define FOG_HEIGHT_DENSITY(a) a.s5
+#define FOG_HEIGHT_FALLOFF(a) a.s6
+
 #define SPHERE 0
 #define SPHERE_POS(a) a.s012
 #define SPHERE_RADIUS(a) a.s3
@@ -39,6 +81,66 @@
 #define BOX_SCALING(a) a.s345
 #define BOX_ROTATION(a) a.s678
 
+#define BVH_STACK_SIZE 32
+#define BVH_NODE_MIN(a) a.s012
+#define BVH_NODE_MAX(a) a.s456
+// the offset and count of a node are in a uint2 next to its bounds.
+#define BVH_NODE_OFFSET(a) a.s0
+#define BVH_NODE_COUNT(a) a.s1
+
+#define TILE_OFFSET(ranges, tile) ranges[2*(tile)]
+#define TILE_COUNT(ranges, tile) ranges[2*(tile) + 1]
+
+// the marching and quality settings, the kernels take the distances as a float8
+// and the counts as a uint2 and put them together.
+struct QualityInfo {
+  float8 dists;
+  uint max_iterations;
+  uint max_reflection_depth;
+};
+
+#define MARCH_RELAXATION(a) a.dists.s0
+#define MARCH_PIXEL_FOOTPRINT(a) a.dists.s1
+#define SMALLEST_DIST(a) a.dists.s2
+#define NORMAL_EPSILON(a) a.dists.s3
+#define MAX_DIST(a) a.dists.s4
+#define MIN_REFLECTION_CUTOFF(a) a.dists.s5
+#define MAX_ITERATIONS(a) a.max_iterations
+#define MAX_REFLECTION_DEPTH(a) a.max_reflection_depth
+
+float3 srgbToLinear(float3 c) {
+  return select(pow((c + (float)0.055)/(float)1.055, (float3)(2.4)), c/(float)12.92, c <= (float)0.04045);
+}
+
+float3 linearToSrgb(float3 c) {
+  return select((float)1.055*pow(c, (float3)(1/2.4)) - (float)0.055, c*(float)12.92, c <= (float)0.0031308);
+}
+
+// John Hable's Uncharted 2 curve.
+float3 filmicCurve(float3 x) {
+  float a = 0.15;
+  float b = 0.50;
+  float c = 0.10;
+  float d = 0.20;
+  float e = 0.02;
+  float f = 0.30;
+  return ((x*(a*x + c*b) + d*e)/(x*(a*x + b) + d*f)) - e/f;
+}
+
+float3 toneMap(float3 color, uint tone_mapping) {
+  switch (tone_mapping) {
+    case TONE_MAPPING_REINHARD:
+      return color/(1 + color);
+    case TONE_MAPPING_ACES:
+      // Krzysztof Narkowicz's fit of the ACES filmic curve.
+      return (color*((float)2.51*color + (float)0.03))/(color*((float)2.43*color + (float)0.59) + (float)0.14);
+    case TONE_MAPPING_FILMIC:
+      return filmicCurve(2*color)/filmicCurve((float3)(11.2));
+    default:
+      return color;
+  }
+}
+
 struct ClosePoint {
   float3 point;
   uint iterations;
@@ -126,107 +228,265 @@ float boxDist( float16 box_data, float3 point)
   return fast_length(fmax(q,((float)0))) + fmin(fmax(q.x,fmax(q.y,q.z)),(float)0);
 }
 
-struct SceneDist distToScene(__constant uchar8* scene_object_integer_data_buffer,
-              __constant float16* scene_object_float_data_buffer,
+float objectDist(uchar8 integer_data, float16 float_data, float3 point) {
+  switch ( OBJECT_TYPE(integer_data) ) {
+    case SPHERE:
+      return sphereDist(float_data, point);
+    case FLOORPLANE:
+      return floorplaneDist(float_data, point);
+    case CAPSULE:
+      return capsuleDist(float_data, point);
+    case CYLINDER:
+      return cylinderDist(float_data, point);
+    case BOX:
+      return boxDist(float_data, point);
+    default:
+      return FLT_MAX;
+  }
+}
+
+// distance from the point to the box, 0 inside it. never more than the distance
+// to anything inside the box.
+float boundsDist(float8 node, float3 point) {
+  return fast_length(fmax(fmax(BVH_NODE_MIN(node) - point, point - BVH_NODE_MAX(node)), (float)0));
+}
+
+struct SceneDist distToScene(__global const uchar8* scene_object_integer_data_buffer,
+              __global const float16* scene_object_float_data_buffer,
               uint num_scene_objects,
+              __global const float8* bvh_nodes,
+              __global const uint2* bvh_node_ranges,
+              __global const uint* bvh_indices,
+              uint num_bvh_nodes,
+              uint num_unbounded_objects,
               float3 point) {
   float min_dist = FLT_MAX;
   uint min_obj = 0;
-  for(uint i = 0; i < num_scene_objects; i++){
-    float dist;
-    switch ( OBJECT_TYPE(scene_object_integer_data_buffer[i]) ) {
-      case SPHERE:
-        dist = sphereDist(scene_object_float_data_buffer[i], point);
-        break;
-      case FLOORPLANE:
-        dist = floorplaneDist(scene_object_float_data_buffer[i], point);
-        break;
-      case CAPSULE:
-        dist = capsuleDist(scene_object_float_data_buffer[i], point);
-        break;
-      case CYLINDER:
-        dist = cylinderDist(scene_object_float_data_buffer[i], point);
-        break;
-      case BOX:
-        dist = boxDist(scene_object_float_data_buffer[i], point);
-        break;
-      default:
-        dist = FLT_MAX;
-        break;
-    }
+
+  // the objects without bounds come first and are always evaluated.
+  for(uint i = 0; i < num_unbounded_objects; i++){
+    uint obj = bvh_indices[i];
+    float dist = objectDist(scene_object_integer_data_buffer[obj], scene_object_float_data_buffer[obj], point);
     if (dist < min_dist) {
       min_dist = dist;
-      min_obj = i;
+      min_obj = obj;
+    }
+  }
+
+  // the tree is split at the median, so its depth stays far below the stack size.
+  uint stack[BVH_STACK_SIZE];
+  uint stack_size = 0;
+  if (num_bvh_nodes > 0) {
+    stack[stack_size++] = 0;
+  }
+  while (stack_size > 0) {
+    uint node_index = stack[--stack_size];
+    float8 node = bvh_nodes[node_index];
+    if (boundsDist(node, point) >= min_dist) {
+      continue;
+    }
+
+    uint2 range = bvh_node_ranges[node_index];
+    uint count = BVH_NODE_COUNT(range);
+    if (count > 0) {
+      for(uint i = BVH_NODE_OFFSET(range); i < BVH_NODE_OFFSET(range) + count; i++){
+        uint obj = bvh_indices[i];
+        float dist = objectDist(scene_object_integer_data_buffer[obj], scene_object_float_data_buffer[obj], point);
+        if (dist < min_dist) {
+          min_dist = dist;
+          min_obj = obj;
+        }
+      }
+    } else {
+      // the nearer child is visited first so the farther one is more likely skipped.
+      uint first = node_index + 1;
+      uint second = BVH_NODE_OFFSET(range);
+      if (boundsDist(bvh_nodes[first], point) < boundsDist(bvh_nodes[second], point)) {
+        stack[stack_size++] = second;
+        stack[stack_size++] = first;
+      } else {
+        stack[stack_size++] = first;
+        stack[stack_size++] = second;
+      }
     }
   }
   return (struct SceneDist){min_dist, min_obj};
 }
 
-struct ClosePoint getPointAtScene( __constant uchar8* scene_object_integer_data_buffer,
-                      __constant float16* scene_object_float_data_buffer,
+// marches from start along direction until a point is within the hit distance
+// of an object. a relaxation above 1 over-relaxes every step, from Keinert et al.
+// "Enhanced Sphere Tracing", and falls back to plain steps once a step overshoots,
+// which shows as the distance bounds of two consecutive points not overlapping.
+// with a pixel footprint the hit distance grows with the distance marched, so far
+// away surfaces aren't marched to finer than the pixel they cover.
+struct ClosePoint getPointAtScene( __global const uchar8* scene_object_integer_data_buffer,
+                      __global const float16* scene_object_float_data_buffer,
                       uint num_scene_objects,
+                      __global const float8* bvh_nodes,
+                      __global const uint2* bvh_node_ranges,
+                      __global const uint* bvh_indices,
+                      uint num_bvh_nodes,
+                      uint num_unbounded_objects,
+                      struct QualityInfo quality_info,
                       float3 direction,
                       float3 start) {
-  float3 curr_point = start;
+  float relaxation = MARCH_RELAXATION(quality_info);
+  float footprint = MARCH_PIXEL_FOOTPRINT(quality_info);
+  float march_dist = 0;
+  float step = 0;
+  float last_dist = 0;
   uint iterations = 0;
   uint obj_index = 0;
-  float dist_to_scene = MAX_DIST-SMALLEST_DIST;
-  while(dist_to_scene < MAX_DIST && dist_to_scene > SMALLEST_DIST && iterations < MAX_ITERATIONS){
+  float dist_to_scene = MAX_DIST(quality_info);
+  bool hit = false;
+  while(iterations < MAX_ITERATIONS(quality_info)){
     struct SceneDist to_scene = distToScene(scene_object_integer_data_buffer, 
                                       scene_object_float_data_buffer, 
                                       num_scene_objects, 
-                                      curr_point);
+                                      bvh_nodes,
+                                      bvh_node_ranges,
+                                      bvh_indices,
+                                      num_bvh_nodes,
+                                      num_unbounded_objects,
+                                      start + direction*march_dist);
+    iterations++;
 
     dist_to_scene = to_scene.dist;
     obj_index = to_scene.obj_index;
 
-    curr_point = curr_point + direction*dist_to_scene;
-    iterations++;
+    if (relaxation > 1 && dist_to_scene + last_dist < step) {
+      // go back to a plain step from the last point.
+      march_dist += last_dist - step;
+      step = last_dist;
+      relaxation = 1;
+      continue;
+    }
+
+    if (dist_to_scene <= fmax(SMALLEST_DIST(quality_info), footprint*march_dist)) {
+      hit = true;
+      break;
+    }
+    if (dist_to_scene >= MAX_DIST(quality_info)) {
+      break;
+    }
+
+    last_dist = dist_to_scene;
+    step = dist_to_scene*relaxation;
+    march_dist += step;
   }
-  bool out_of_bounds = dist_to_scene >= MAX_DIST || iterations >= MAX_ITERATIONS;
-  return (struct ClosePoint){curr_point, iterations, obj_index, out_of_bounds};
+  float3 point = start + direction*(march_dist + dist_to_scene);
+  return (struct ClosePoint){point, iterations, obj_index, !hit};
 }
 
-float3 getNormal(__constant uchar8* scene_object_integer_data_buffer,
-                __constant float16* scene_object_float_data_buffer,
+float3 getNormal(__global const uchar8* scene_object_integer_data_buffer,
+                __global const float16* scene_object_float_data_buffer,
                 uint num_scene_objects,
+                __global const float8* bvh_nodes,
+                __global const uint2* bvh_node_ranges,
+                __global const uint* bvh_indices,
+                uint num_bvh_nodes,
+                uint num_unbounded_objects,
+                struct QualityInfo quality_info,
                 float3 point) {
   
   float dist = distToScene(scene_object_integer_data_buffer,
                           scene_object_float_data_buffer,
                           num_scene_objects,
+                          bvh_nodes,
+                          bvh_node_ranges,
+                          bvh_indices,
+                          num_bvh_nodes,
+                          num_unbounded_objects,
                           point).dist;
 
-  float3 dx = point - (float3)(SMALLEST_DIST, 0, 0);
-  float3 dy = point - (float3)(0, SMALLEST_DIST, 0);
-  float3 dz = point - (float3)(0, 0, SMALLEST_DIST);
+  float3 dx = point - (float3)(SMALLEST_DIST(quality_info), 0, 0);
+  float3 dy = point - (float3)(0, SMALLEST_DIST(quality_info), 0);
+  float3 dz = point - (float3)(0, 0, SMALLEST_DIST(quality_info));
   
   float normx = dist - distToScene(scene_object_integer_data_buffer,
                                   scene_object_float_data_buffer,
                                   num_scene_objects,
+                                  bvh_nodes,
+                                  bvh_node_ranges,
+                                  bvh_indices,
+                                  num_bvh_nodes,
+                                  num_unbounded_objects,
                                   dx).dist;
   
   float normy = dist - distToScene(scene_object_integer_data_buffer,
                                   scene_object_float_data_buffer,
                                   num_scene_objects,
+                                  bvh_nodes,
+                                  bvh_node_ranges,
+                                  bvh_indices,
+                                  num_bvh_nodes,
+                                  num_unbounded_objects,
                                   dy).dist;
 
   float normz = dist - distToScene(scene_object_integer_data_buffer,
                                   scene_object_float_data_buffer,
                                   num_scene_objects,
+                                  bvh_nodes,
+                                  bvh_node_ranges,
+                                  bvh_indices,
+                                  num_bvh_nodes,
+                                  num_unbounded_objects,
                                   dz).dist;
                                   
   return fast_normalize((float3)(normx,normy,normz));
 }
 
-float getLight (__constant uchar8* scene_object_integer_data_buffer,
-                __constant float16* scene_object_float_data_buffer,
+// 1 when nothing is between the point and the light, 0.2 in shadow.
+float getShadow(__global const uchar8* scene_object_integer_data_buffer,
+                __global const float16* scene_object_float_data_buffer,
+                uint num_scene_objects,
+                __global const float8* bvh_nodes,
+                __global const uint2* bvh_node_ranges,
+                __global const uint* bvh_indices,
+                uint num_bvh_nodes,
+                uint num_unbounded_objects,
+                struct QualityInfo quality_info,
+                float3 point,
+                float3 normal,
+                float3 light){
+  struct ClosePoint d = getPointAtScene(scene_object_integer_data_buffer, 
+                            scene_object_float_data_buffer, 
+                            num_scene_objects, 
+                            bvh_nodes,
+                            bvh_node_ranges,
+                            bvh_indices,
+                            num_bvh_nodes,
+                            num_unbounded_objects,
+                            quality_info,
+                            fast_normalize(light - point), 
+                            point + normal*NORMAL_EPSILON(quality_info));
+
+  if (fast_length(point - d.point) < fast_length(point - light)) {
+    return (float)0.2;
+  }
+  return 1;
+}
+
+float getLight (__global const uchar8* scene_object_integer_data_buffer,
+                __global const float16* scene_object_float_data_buffer,
                 uint num_scene_objects,
+                __global const float8* bvh_nodes,
+                __global const uint2* bvh_node_ranges,
+                __global const uint* bvh_indices,
+                uint num_bvh_nodes,
+                uint num_unbounded_objects,
+                struct QualityInfo quality_info,
                 float3 point,
                 float3 light){
   float3 scene_normal = getNormal(scene_object_integer_data_buffer,
                                 scene_object_float_data_buffer,
                                 num_scene_objects,
+                                bvh_nodes,
+                                bvh_node_ranges,
+                                bvh_indices,
+                                num_bvh_nodes,
+                                num_unbounded_objects,
+                                quality_info,
                                 point);
 
   float3 to_light = fast_normalize(light - point);
@@ -234,102 +494,1281 @@ float getLight (__constant uchar8* scene_object_integer_data_buffer,
   
   light_val = clamp(light_val, (float)0 , (float)1);
 
-  struct ClosePoint d = getPointAtScene(scene_object_integer_data_buffer, 
-                            scene_object_float_data_buffer, 
-                            num_scene_objects, 
-                            to_light, 
-                            point + scene_normal*NORMAL_EPSILON);
+  return light_val*getShadow(scene_object_integer_data_buffer,
+                            scene_object_float_data_buffer,
+                            num_scene_objects,
+                            bvh_nodes,
+                            bvh_node_ranges,
+                            bvh_indices,
+                            num_bvh_nodes,
+                            num_unbounded_objects,
+                            quality_info,
+                            point,
+                            scene_normal,
+                            light);
+}
 
-  if (fast_length(point - d.point) < fast_length(point - light)) {
-    light_val = light_val*0.2;
+float3 getReflection(float3 in, float3 normal) {
+  return in - 2*dot(in,normal)*normal;
+}
+
+float fogDensity(float8 fog_info, float3 point) {
+  float height_fog = FOG_HEIGHT_DENSITY(fog_info)*exp(-FOG_HEIGHT_FALLOFF(fog_info)*(point.s1 - FOG_HEIGHT(fog_info)));
+  return FOG_DENSITY(fog_info) + height_fog;
+}
+
+// integral of the fog density along the ray from start to start + direction*dist.
+float fogOpticalDepth(float8 fog_info, float3 start, float3 direction, float dist) {
+  float falloff = FOG_HEIGHT_FALLOFF(fog_info);
+  float height_density = FOG_HEIGHT_DENSITY(fog_info)*exp(-falloff*(start.s1 - FOG_HEIGHT(fog_info)));
+  float fall = falloff*direction.s1;
+
+  float depth = FOG_DENSITY(fog_info)*dist;
+  // the height fog integral has a removable singularity for horizontal rays.
+  if (fabs(fall) > (float)0.0001) {
+    depth += height_density*(1 - exp(-fall*dist))/fall;
   }
+  else {
+    depth += height_density*dist;
+  }
+  return depth;
+}
+
+// single scattering of the point light along the ray, each sample is lit if
+// nothing is between it and the light.
+float getGodRays(__global const uchar8* scene_object_integer_data_buffer,
+                __global const float16* scene_object_float_data_buffer,
+                uint num_scene_objects,
+                __global const float8* bvh_nodes,
+                __global const uint2* bvh_node_ranges,
+                __global const uint* bvh_indices,
+                uint num_bvh_nodes,
+                uint num_unbounded_objects,
+                struct QualityInfo quality_info,
+                float8 fog_info,
+                float3 light,
+                float3 start,
+                float3 direction,
+                float dist,
+                uint samples) {
+  float step = dist/(float)samples;
+  float scattered = 0;
+  for(uint i = 0; i < samples; i++){
+    float t = step*((float)i + (float)0.5);
+    float3 point = start + direction*t;
+    float light_dist = fast_distance(point, light);
 
-  return light_val;
+    struct ClosePoint d = getPointAtScene(scene_object_integer_data_buffer, 
+                              scene_object_float_data_buffer, 
+                              num_scene_objects, 
+                              bvh_nodes,
+                              bvh_node_ranges,
+                              bvh_indices,
+                              num_bvh_nodes,
+                              num_unbounded_objects,
+                              quality_info,
+                              fast_normalize(light - point), 
+                              point);
+
+    if (fast_length(point - d.point) >= light_dist) {
+      float transmittance = exp(-fogOpticalDepth(fog_info, start, direction, t));
+      scattered += fogDensity(fog_info, point)*transmittance*step;
+    }
+  }
+  return scattered;
 }
 
-float3 getReflection(float3 in, float3 normal) {
-  return in - 2*dot(in,normal)*normal;
+uint hashUint(uint x) {
+  x ^= x >> 16;
+  x *= 0x7feb352d;
+  x ^= x >> 15;
+  x *= 0x846ca68b;
+  x ^= x >> 16;
+  return x;
+}
+
+// blue through cyan, green and yellow to red as t goes from 0 to 1.
+float3 heatColor(float t) {
+  float4 x = (float4)(4*clamp(t, (float)0, (float)1));
+  return clamp((float3)(1.5) - fabs(x.xyz - (float3)(3, 2, 1)), (float)0, (float)1);
+}
+
+// what a debug view shows for the primary ray that marched from start_point to d.
+float3 debugColor(__global const uchar8* scene_object_integer_data_buffer,
+                __global const float16* scene_object_float_data_buffer,
+                uint num_scene_objects,
+                __global const float8* bvh_nodes,
+                __global const uint2* bvh_node_ranges,
+                __global const uint* bvh_indices,
+                uint num_bvh_nodes,
+                uint num_unbounded_objects,
+                struct QualityInfo quality_info,
+                uint debug_view,
+                struct ClosePoint d,
+                float3 start_point,
+                float3 light_pos) {
+  if (debug_view == DEBUG_ITERATIONS) {
+    return srgbToLinear(heatColor((float)d.iterations/(float)MAX_ITERATIONS(quality_info)));
+  }
+  if (d.out_of_bounds) {
+    return (float3)(0);
+  }
+
+  float3 normal = getNormal(scene_object_integer_data_buffer,
+                          scene_object_float_data_buffer,
+                          num_scene_objects,
+                          bvh_nodes,
+                          bvh_node_ranges,
+                          bvh_indices,
+                          num_bvh_nodes,
+                          num_unbounded_objects,
+                          quality_info,
+                          d.point);
+  switch (debug_view) {
+    case DEBUG_NORMALS:
+      return srgbToLinear(normal*(float)0.5 + (float)0.5);
+    case DEBUG_DEPTH:
+      // near is white, fading to black at the largest distance.
+      return srgbToLinear((float3)(1 - fast_distance(start_point, d.point)/MAX_DIST(quality_info)));
+    case DEBUG_OBJECT_ID: {
+      uint hash = hashUint(d.obj_index + 1);
+      return srgbToLinear((float3)((float)(hash & 0xFF), (float)((hash >> 8) & 0xFF), (float)((hash >> 16) & 0xFF))/(float)255);
+    }
+    default:
+      return (float3)(getShadow(scene_object_integer_data_buffer,
+                              scene_object_float_data_buffer,
+                              num_scene_objects,
+                              bvh_nodes,
+                              bvh_node_ranges,
+                              bvh_indices,
+                              num_bvh_nodes,
+                              num_unbounded_objects,
+                              quality_info,
+                              d.point,
+                              normal,
+                              light_pos));
+  }
 }
 
-uchar3 rayCastHelper(__constant uchar8* scene_object_integer_data_buffer,
-                  __constant float16* scene_object_float_data_buffer,
+// follows the ray through its mirror reflections in a loop, starting at the given
+// reflection depth. each surface adds its own shading and fog weighted by the
+// throughput, the product of the reflectivity and fog transmittance of the
+// surfaces before it.
+float3 rayCastHelper(__global const uchar8* scene_object_integer_data_buffer,
+                  __global const float16* scene_object_float_data_buffer,
                   uint num_scene_objects,
+                  __global const float8* bvh_nodes,
+                  __global const uint2* bvh_node_ranges,
+                  __global const uint* bvh_indices,
+                  uint num_bvh_nodes,
+                  uint num_unbounded_objects,
+                  struct QualityInfo quality_info,
+                  __global const uint* primary_indices,
+                  uint num_primary_nodes,
+                  uint num_primary_unbounded_objects,
+                  float4 primary_cone,
+                  uint* primary_iterations,
                   float3 light_pos,
+                  float8 fog_info,
+                  uint god_ray_samples,
+                  uint debug_view,
                   float3 start_point,
                   float3 direction,
-                  uint reflect_depth){
+                  uint first_reflect_depth){
+  float3 fog_color = UCHAR3_TO_LINEAR(FOG_COLOR(fog_info));
+  float3 radiance = (float3)(0);
+  float throughput = 1;
 
-  struct ClosePoint d = getPointAtScene(scene_object_integer_data_buffer, 
-                              scene_object_float_data_buffer, 
-                              num_scene_objects, 
-                              direction, 
-                              start_point);
-
-  float light = getLight( scene_object_integer_data_buffer, 
-                          scene_object_float_data_buffer, 
+  for(uint reflect_depth = first_reflect_depth; reflect_depth <= MAX_REFLECTION_DEPTH(quality_info); reflect_depth++){
+    // only the primary ray marches through the objects culled for its tile, and
+    // starts where the cone of its block hit something, primary_cone.w from the
+    // apex in primary_cone.xyz.
+    bool primary = reflect_depth == 0;
+    float3 march_start = start_point;
+    if (primary) {
+      march_start += direction*fmax((float)0, primary_cone.w - distance(start_point, primary_cone.xyz));
+    }
+    struct ClosePoint d = getPointAtScene(scene_object_integer_data_buffer, 
+                                scene_object_float_data_buffer, 
+                                num_scene_objects, 
+                                bvh_nodes,
+                                bvh_node_ranges,
+                                primary ? primary_indices : bvh_indices,
+                                primary ? num_primary_nodes : num_bvh_nodes,
+                                primary ? num_primary_unbounded_objects : num_unbounded_objects,
+                                quality_info,
+                                direction, 
+                                march_start);
+    if (primary) {
+      *primary_iterations += d.iterations;
+      if (debug_view != DEBUG_SHADED) {
+        return debugColor(scene_object_integer_data_buffer,
+                          scene_object_float_data_buffer,
                           num_scene_objects,
-                          d.point,
+                          bvh_nodes,
+                          bvh_node_ranges,
+                          bvh_indices,
+                          num_bvh_nodes,
+                          num_unbounded_objects,
+                          quality_info,
+                          debug_view,
+                          d,
+                          start_point,
                           light_pos);
-  
-  uchar3 color = OBJECT_COLOR(scene_object_integer_data_buffer[d.obj_index]);
+      }
+    }
+
+    float light = getLight( scene_object_integer_data_buffer, 
+                            scene_object_float_data_buffer, 
+                            num_scene_objects,
+                            bvh_nodes,
+                            bvh_node_ranges,
+                            bvh_indices,
+                            num_bvh_nodes,
+                            num_unbounded_objects,
+                            quality_info,
+                            d.point,
+                            light_pos);
+    
+    float3 color = UCHAR3_TO_LINEAR(OBJECT_COLOR(scene_object_integer_data_buffer[d.obj_index]));
+
+    float reflectivity = REFLECTIVITY(scene_object_float_data_buffer[d.obj_index]);
+
+    float march_dist = d.out_of_bounds ? MAX_DIST(quality_info) : fast_distance(start_point, d.point);
+    float fog = 1 - exp(-fogOpticalDepth(fog_info, start_point, direction, march_dist));
+
+    if(primary && god_ray_samples > 0){
+      radiance += getGodRays(scene_object_integer_data_buffer,
+                            scene_object_float_data_buffer,
+                            num_scene_objects,
+                            bvh_nodes,
+                            bvh_node_ranges,
+                            bvh_indices,
+                            num_bvh_nodes,
+                            num_unbounded_objects,
+                            quality_info,
+                            fog_info,
+                            light_pos,
+                            start_point,
+                            direction,
+                            march_dist,
+                            god_ray_samples);
+    }
+
+    // the cutoff shrinks the deeper the reflection, the primary ray reflects
+    // off anything reflective.
+    bool reflects = !d.out_of_bounds
+                    && reflect_depth < MAX_REFLECTION_DEPTH(quality_info)
+                    && reflectivity > 0
+                    && (primary || reflectivity/(float)reflect_depth >= MIN_REFLECTION_CUTOFF(quality_info));
+    if(!reflects){
+      radiance += throughput*mix(color*light, fog_color, fog);
+      break;
+    }
+
+    radiance += throughput*mix(color*light*(1 - reflectivity), fog_color, fog);
+    throughput *= (1 - fog)*reflectivity;
+
+    float3 scene_normal = getNormal(scene_object_integer_data_buffer,
+                                  scene_object_float_data_buffer,
+                                  num_scene_objects,
+                                  bvh_nodes,
+                                  bvh_node_ranges,
+                                  bvh_indices,
+                                  num_bvh_nodes,
+                                  num_unbounded_objects,
+                                  quality_info,
+                                  d.point);
+
+    start_point = d.point + scene_normal*NORMAL_EPSILON(quality_info);
+    direction = getReflection(direction, scene_normal);
+  }
+
+  return radiance;
+}
 
-  float reflectivity = REFLECTIVITY(scene_object_float_data_buffer[d.obj_index]);
+// uniform float in [0, 1) from a hashed seed.
+float hashToFloat(uint x) {
+  return (float)(hashUint(x) >> 8)/(float)(1 << 24);
+}
 
-  if(d.out_of_bounds || reflect_depth >= MAX_REFLECTION_DEPTH || reflectivity/(float)reflect_depth < MIN_REFLECTION_CUTOFF){
-    return SCALE_UCHAR3_BY_FLOAT(color, light);
+float2 hashToFloat2(uint x) {
+  return (float2)(hashToFloat(2*x), hashToFloat(2*x + 1));
+}
+
+uint getSampleCount(uint sample_pattern, uint samples_per_axis) {
+  switch (sample_pattern) {
+    case AA_GRID:
+    case AA_STRATIFIED:
+      return samples_per_axis*samples_per_axis;
+    case AA_ROTATED_GRID:
+      return 4;
+    default:
+      return 1;
   }
+}
 
-  float3 scene_normal = getNormal(scene_object_integer_data_buffer,
-                                scene_object_float_data_buffer,
-                                num_scene_objects,
-                                d.point);
+// offset of the sample from the pixel center in pixels, each axis in [-0.5, 0.5).
+float2 getSampleOffset(uint sample_pattern, uint samples_per_axis, uint sample, uint pixel) {
+  float2 cell = (float2)((float)(sample % samples_per_axis), (float)(sample / samples_per_axis));
+  switch (sample_pattern) {
+    case AA_GRID:
+      return (cell + (float)0.5)/(float)samples_per_axis - (float)0.5;
+    case AA_ROTATED_GRID:
+      switch (sample) {
+        case 0: return (float2)(0.125, 0.375);
+        case 1: return (float2)(0.375, -0.125);
+        case 2: return (float2)(-0.125, -0.375);
+        default: return (float2)(-0.375, 0.125);
+      }
+    case AA_STRATIFIED: {
+      float2 jitter = hashToFloat2(hashUint(pixel) ^ hashUint(sample));
+      return (cell + jitter)/(float)samples_per_axis - (float)0.5;
+    }
+    default:
+      return (float2)(0);
+  }
+}
+
+// primary ray through the given pixel position, false if the pixel is not
+// covered by the projection.
+bool getPinholeRay(float16 camera_info,
+                  float2 pixel,
+                  uint width,
+                  uint height,
+                  float3* start_point,
+                  float3* direction) {
+  float3 camera_pos = CAMERA_POS(camera_info);
+  float3 right = CAMERA_RIGHT(camera_info);
+  float3 up = CAMERA_UP(camera_info);
+  float3 forward = CAMERA_FORWARD(camera_info);
+
+  switch (CAMERA_PROJECTION(camera_info)) {
+    case PROJECTION_PERSPECTIVE: {
+      float tan_half_fov = PERSPECTIVE_TAN_HALF_FOV(camera_info);
+      float aspect = PERSPECTIVE_ASPECT(camera_info) > 0 ? PERSPECTIVE_ASPECT(camera_info) : (float)width/(float)height;
 
-  float3 new_direction = getReflection(direction, scene_normal);
+      // pixel centers mapped to [-1, 1] on both axes.
+      float ndcx = 2*(pixel.x + (float)0.5)/(float)width - 1;
+      float ndcy = 1 - 2*(pixel.y + (float)0.5)/(float)height;
 
-  uchar3 reflect_color = rayCastHelper(scene_object_integer_data_buffer,
-                                      scene_object_float_data_buffer,
-                                      num_scene_objects,
-                                      light_pos,
-                                      d.point + scene_normal*NORMAL_EPSILON,
-                                      new_direction,
-                                      reflect_depth + 1);
+      *direction = fast_normalize(right*(ndcx*tan_half_fov*aspect + CAMERA_VIEW_SHIFT(camera_info)) + up*ndcy*tan_half_fov + forward);
+      *start_point = camera_pos + *direction*(PERSPECTIVE_NEAR(camera_info)/dot(*direction, forward));
+      return true;
+    }
+    case PROJECTION_ORTHOGRAPHIC: {
+      float view_height = ORTHOGRAPHIC_HEIGHT(camera_info);
+      float offx = (pixel.x + (float)0.5 - (float)width/2)*view_height/(float)height;
+      float offy = ((float)height/2 - pixel.y - (float)0.5)*view_height/(float)height;
+
+      *direction = forward;
+      *start_point = camera_pos + right*offx + up*offy;
+      return true;
+    }
+    case PROJECTION_FISHEYE: {
+      // the image circle is inscribed in the shorter side.
+      float radius = (float)min(width, height)/2;
+      float u = (pixel.x + (float)0.5 - (float)width/2)/radius;
+      float v = ((float)height/2 - pixel.y - (float)0.5)/radius;
+      float r = sqrt(u*u + v*v);
+      if (r > 1) {
+        return false;
+      }
+
+      // equidistant, the angle from the look direction grows linearly with r.
+      float theta = r*FISHEYE_HALF_ANGLE(camera_info);
+      float phi = atan2(v, u);
+
+      *direction = fast_normalize(forward*cos(theta) + (right*cos(phi) + up*sin(phi))*sin(theta));
+      *start_point = camera_pos;
+      return true;
+    }
+    case PROJECTION_EQUIRECTANGULAR: {
+      float longitude = (2*(pixel.x + (float)0.5)/(float)width - 1)*M_PI_F;
+      float latitude = (1 - 2*(pixel.y + (float)0.5)/(float)height)*M_PI_2_F;
+
+      *direction = fast_normalize(forward*cos(latitude)*cos(longitude) + right*cos(latitude)*sin(longitude) + up*sin(latitude));
+      *start_point = camera_pos;
+      return true;
+    }
+    default: {
+      float scale = LEGACY_SCALE(camera_info);
+      float offx = (pixel.x - (float)width/2)/scale;
+      float offy = ((float)height/2 - pixel.y)/scale;
 
-  return SCALE_UCHAR3_BY_FLOAT(color, light*(1.-reflectivity)) + SCALE_UCHAR3_BY_FLOAT(reflect_color, reflectivity);
+      float frame_dist = LEGACY_FRAME_DIST(camera_info);
+      *direction = fast_normalize(right*(offx + CAMERA_VIEW_SHIFT(camera_info)*frame_dist) + up*offy + forward*frame_dist);
+      *start_point = camera_pos + right*offx + up*offy;
+      return true;
+    }
+  }
+}
+
+// orthonormal basis around n, from Duff et al. "Building an Orthonormal Basis, Revisited".
+void getBasis(float3 n, float3* tangent, float3* bitangent) {
+  float sign = copysign((float)1, n.z);
+  float a = -1/(sign + n.z);
+  float b = n.x*n.y*a;
+  *tangent = (float3)(1 + sign*n.x*n.x*a, sign*b, -sign*n.x);
+  *bitangent = (float3)(b, sign + n.y*n.y*a, -n.y);
+}
+
+// pinhole ray bent through a point of the thin lens so that it still meets the
+// pinhole ray on the focus plane. the rays of fisheye and equirectangular reach
+// the sides and back, where the plane is parallel to or behind them, so they
+// focus on the sphere around the camera through a lens facing along the ray.
+// lens_sample is uniform in [0, 1).
+bool getCameraRay(float16 camera_info,
+                  float2 pixel,
+                  float2 lens_sample,
+                  uint width,
+                  uint height,
+                  float3* start_point,
+                  float3* direction) {
+  if (!getPinholeRay(camera_info, pixel, width, height, start_point, direction)) {
+    return false;
+  }
+
+  float aperture = CAMERA_APERTURE(camera_info);
+  if (aperture <= 0) {
+    return true;
+  }
+
+  float3 focus_point;
+  float3 lens_right;
+  float3 lens_up;
+  uint projection = CAMERA_PROJECTION(camera_info);
+  if (projection == PROJECTION_FISHEYE || projection == PROJECTION_EQUIRECTANGULAR) {
+    focus_point = *start_point + *direction*CAMERA_FOCUS_DIST(camera_info);
+    getBasis(*direction, &lens_right, &lens_up);
+  }
+  else {
+    float3 forward = CAMERA_FORWARD(camera_info);
+    focus_point = *start_point + *direction*(CAMERA_FOCUS_DIST(camera_info)/dot(*direction, forward));
+    lens_right = CAMERA_RIGHT(camera_info);
+    lens_up = CAMERA_UP(camera_info);
+  }
+
+  // uniform point on the lens disk.
+  float r = aperture*sqrt(lens_sample.x);
+  float theta = 2*M_PI_F*lens_sample.y;
+  *start_point += lens_right*r*cos(theta) + lens_up*r*sin(theta);
+  *direction = fast_normalize(focus_point - *start_point);
+  return true;
+}
+
+float3 tracePixelSample(__global const uchar8* scene_object_integer_data_buffer,
+                  __global const float16* scene_object_float_data_buffer,
+                  uint num_scene_objects,
+                  __global const float8* bvh_nodes,
+                  __global const uint2* bvh_node_ranges,
+                  __global const uint* bvh_indices,
+                  uint num_bvh_nodes,
+                  uint num_unbounded_objects,
+                  struct QualityInfo quality_info,
+                  __global const uint* primary_indices,
+                  uint num_primary_nodes,
+                  uint num_primary_unbounded_objects,
+                  float4 primary_cone,
+                  uint* primary_iterations,
+                  float16 camera_info,
+                  float3 light_pos,
+                  float8 fog_info,
+                  uint god_ray_samples,
+                  uint debug_view,
+                  float2 pixel,
+                  float2 lens_sample,
+                  uint width, 
+                  uint height) {
+  float3 start_point;
+  float3 direction;
+  if (!getCameraRay(camera_info, pixel, lens_sample, width, height, &start_point, &direction)) {
+    return (float3)(0);
+  }
+
+  return rayCastHelper(scene_object_integer_data_buffer,
+                      scene_object_float_data_buffer,
+                      num_scene_objects,
+                      bvh_nodes,
+                      bvh_node_ranges,
+                      bvh_indices,
+                      num_bvh_nodes,
+                      num_unbounded_objects,
+                      quality_info,
+                      primary_indices,
+                      num_primary_nodes,
+                      num_primary_unbounded_objects,
+                      primary_cone,
+                      primary_iterations,
+                      light_pos,
+                      fog_info,
+                      god_ray_samples,
+                      debug_view,
+                      start_point,
+                      direction,
+                      0);
+}
+
+// xorshift32, the state must never be zero.
+float randomFloat(uint* state) {
+  uint x = *state;
+  x ^= x << 13;
+  x ^= x >> 17;
+  x ^= x << 5;
+  *state = x;
+  return (float)(x >> 8)/(float)(1 << 24);
+}
+
+// direction around axis with pdf proportional to cos(theta)^exponent,
+// an exponent of 1 is the cosine weighted hemisphere.
+float3 sampleLobe(float3 axis, float exponent, uint* rng) {
+  float cos_theta = pow(randomFloat(rng), 1/(exponent + 1));
+  float sin_theta = sqrt(fmax((float)0, 1 - cos_theta*cos_theta));
+  float phi = 2*M_PI_F*randomFloat(rng);
+
+  float3 tangent;
+  float3 bitangent;
+  getBasis(axis, &tangent, &bitangent);
+  return fast_normalize(tangent*cos(phi)*sin_theta + bitangent*sin(phi)*sin_theta + axis*cos_theta);
+}
+
+// radiance arriving at point from the point light, zero when in shadow.
+float3 getDirectLight(__global const uchar8* scene_object_integer_data_buffer,
+                __global const float16* scene_object_float_data_buffer,
+                uint num_scene_objects,
+                __global const float8* bvh_nodes,
+                __global const uint2* bvh_node_ranges,
+                __global const uint* bvh_indices,
+                uint num_bvh_nodes,
+                uint num_unbounded_objects,
+                struct QualityInfo quality_info,
+                float3 point,
+                float3 normal,
+                float3 light) {
+  float3 to_light = light - point;
+  float light_dist = fast_length(to_light);
+  to_light = to_light/light_dist;
+
+  float cos_light = dot(to_light, normal);
+  if (cos_light <= 0) {
+    return (float3)(0);
+  }
+
+  struct ClosePoint d = getPointAtScene(scene_object_integer_data_buffer, 
+                            scene_object_float_data_buffer, 
+                            num_scene_objects, 
+                            bvh_nodes,
+                            bvh_node_ranges,
+                            bvh_indices,
+                            num_bvh_nodes,
+                            num_unbounded_objects,
+                            quality_info,
+                            to_light, 
+                            point);
+
+  if (fast_length(point - d.point) < light_dist) {
+    return (float3)(0);
+  }
+
+  return (float3)(POINT_LIGHT_INTENSITY*cos_light/(light_dist*light_dist));
+}
+
+// one path of the monte carlo integrator. surfaces are a mix of a lambertian
+// lobe and a specular lobe chosen with probability reflectivity, lights are the
+// point light and any emissive object, and the fog color lights the sky.
+float3 pathTrace(__global const uchar8* scene_object_integer_data_buffer,
+                __global const float16* scene_object_float_data_buffer,
+                uint num_scene_objects,
+                __global const float8* bvh_nodes,
+                __global const uint2* bvh_node_ranges,
+                __global const uint* bvh_indices,
+                uint num_bvh_nodes,
+                uint num_unbounded_objects,
+                struct QualityInfo quality_info,
+                float3 light_pos,
+                float8 fog_info,
+                float3 start_point,
+                float3 direction,
+                uint max_bounces,
+                uint* rng) {
+  float3 fog_color = UCHAR3_TO_LINEAR(FOG_COLOR(fog_info));
+  float3 radiance = (float3)(0);
+  float3 throughput = (float3)(1);
+
+  for(uint bounce = 0; bounce < max_bounces; bounce++){
+    struct ClosePoint d = getPointAtScene(scene_object_integer_data_buffer, 
+                                scene_object_float_data_buffer, 
+                                num_scene_objects, 
+                                bvh_nodes,
+                                bvh_node_ranges,
+                                bvh_indices,
+                                num_bvh_nodes,
+                                num_unbounded_objects,
+                                quality_info,
+                                direction, 
+                                start_point);
+
+    if (d.out_of_bounds) {
+      radiance += throughput*fog_color;
+      break;
+    }
+
+    float fog = 1 - exp(-fogOpticalDepth(fog_info, start_point, direction, fast_distance(start_point, d.point)));
+    radiance += throughput*fog*fog_color;
+    throughput *= 1 - fog;
+
+    uchar8 integer_data = scene_object_integer_data_buffer[d.obj_index];
+    float16 float_data = scene_object_float_data_buffer[d.obj_index];
+    float3 albedo = UCHAR3_TO_LINEAR(OBJECT_COLOR(integer_data));
+
+    radiance += throughput*albedo*EMISSION(float_data);
+
+    float3 scene_normal = getNormal(scene_object_integer_data_buffer,
+                                  scene_object_float_data_buffer,
+                                  num_scene_objects,
+                                  bvh_nodes,
+                                  bvh_node_ranges,
+                                  bvh_indices,
+                                  num_bvh_nodes,
+                                  num_unbounded_objects,
+                                  quality_info,
+                                  d.point);
+    start_point = d.point + scene_normal*NORMAL_EPSILON(quality_info);
+
+    // the lobe selection probability cancels the lobe weight, and each lobe
+    // is sampled proportionally to its own distribution.
+    if (randomFloat(rng) < REFLECTIVITY(float_data)) {
+      float3 mirror = getReflection(direction, scene_normal);
+      float roughness = ROUGHNESS(float_data);
+      direction = roughness > 0 ? sampleLobe(mirror, 2/(roughness*roughness) - 2, rng) : mirror;
+      if (dot(direction, scene_normal) <= 0) {
+        break;
+      }
+    }
+    else {
+      radiance += throughput*albedo/M_PI_F*getDirectLight(scene_object_integer_data_buffer,
+                                                          scene_object_float_data_buffer,
+                                                          num_scene_objects,
+                                                          bvh_nodes,
+                                                          bvh_node_ranges,
+                                                          bvh_indices,
+                                                          num_bvh_nodes,
+                                                          num_unbounded_objects,
+                                                          quality_info,
+                                                          start_point,
+                                                          scene_normal,
+                                                          light_pos);
+      direction = sampleLobe(scene_normal, 1, rng);
+      throughput *= albedo;
+    }
+
+    if (bounce >= RUSSIAN_ROULETTE_START_BOUNCE) {
+      float survival = clamp(fmax(throughput.x, fmax(throughput.y, throughput.z)), (float)0.05, (float)1);
+      if (randomFloat(rng) >= survival) {
+        break;
+      }
+      throughput /= survival;
+    }
+  }
+  return radiance;
+}
+
+// averages the tone mapped samples of the pattern over the pixel.
+float3 renderPixel(__global const uchar8* scene_object_integer_data_buffer,
+                  __global const float16* scene_object_float_data_buffer,
+                  uint num_scene_objects,
+                  __global const float8* bvh_nodes,
+                  __global const uint2* bvh_node_ranges,
+                  __global const uint* bvh_indices,
+                  uint num_bvh_nodes,
+                  uint num_unbounded_objects,
+                  struct QualityInfo quality_info,
+                  __global const uint* primary_indices,
+                  uint num_primary_nodes,
+                  uint num_primary_unbounded_objects,
+                  float4 primary_cone,
+                  uint* primary_iterations,
+                  float16 camera_info,
+                  float3 light_pos,
+                  float8 fog_info,
+                  uint god_ray_samples,
+                  uint debug_view,
+                  uint tone_mapping,
+                  float exposure,
+                  uint sample_pattern,
+                  uint samples_per_axis,
+                  uint x,
+                  uint y,
+                  uint width, 
+                  uint height) {
+  uint sample_count = getSampleCount(sample_pattern, samples_per_axis);
+  float3 color = (float3)(0);
+  for(uint i = 0; i < sample_count; i++){
+    float2 offset = getSampleOffset(sample_pattern, samples_per_axis, i, y*width + x);
+    float2 lens_sample = hashToFloat2(hashUint(hashUint(y*width + x) ^ i));
+    float3 sample_color = tracePixelSample(scene_object_integer_data_buffer,
+                                          scene_object_float_data_buffer,
+                                          num_scene_objects,
+                                          bvh_nodes,
+                                          bvh_node_ranges,
+                                          bvh_indices,
+                                          num_bvh_nodes,
+                                          num_unbounded_objects,
+                                          quality_info,
+                                          primary_indices,
+                                          num_primary_nodes,
+                                          num_primary_unbounded_objects,
+                                          primary_cone,
+                                          primary_iterations,
+                                          camera_info,
+                                          light_pos,
+                                          fog_info,
+                                          god_ray_samples,
+                                          debug_view,
+                                          (float2)((float)x, (float)y) + offset,
+                                          lens_sample,
+                                          width,
+                                          height);
+    // the debug views are shown as they are.
+    color += debug_view == DEBUG_SHADED ? toneMap(sample_color*exposure, tone_mapping) : sample_color;
+  }
+  return color/(float)sample_count;
+}
+
+uint packColor(float3 color) {
+  uchar3 srgb = convert_uchar3_sat_rte(linearToSrgb(clamp(color, (float)0, (float)1))*255);
+  return (uint)srgb.s0 << 16 | (uint)srgb.s1 << 8 | (uint)srgb.s2;
+}
+
+float packedLuminance(uint color) {
+  float3 rgb = (float3)((float)((color >> 16) & 0xFF), (float)((color >> 8) & 0xFF), (float)(color & 0xFF))/(float)255;
+  return dot(rgb, (float3)(0.2126, 0.7152, 0.0722));
+}
+
+// the first pass of cone marching, one work item per block of cone_size pixels.
+// marches a cone from the apex every primary ray of the block starts from that
+// holds all of them, and stores how far along it the cone is free of objects and
+// how many steps that took. every point of the cone up to the next point is
+// within the distance bound of the current one while the step is no more than
+// (dist - t*tan_angle)/(1 + tan_angle).
+__kernel void coneMarch(__global float2* cone_buffer,
+                  __global const uchar8* scene_object_integer_data_buffer,
+                  __global const float16* scene_object_float_data_buffer,
+                  uint num_scene_objects,
+                  __global const float8* bvh_nodes,
+                  __global const uint2* bvh_node_ranges,
+                  __global const uint* bvh_indices,
+                  uint num_bvh_nodes,
+                  uint num_unbounded_objects,
+                  float8 quality_dists,
+                  uint2 quality_counts,
+                  float16 camera_info,
+                  float3 cone_apex,
+                  uint cone_size,
+                  uint width, 
+                  uint height) {
+  struct QualityInfo quality_info = {quality_dists, quality_counts.s0, quality_counts.s1};
+  uint blocks_x = (width + cone_size - 1)/cone_size;
+  uint block_x = get_global_id(0) % blocks_x;
+  uint block_y = get_global_id(0) / blocks_x;
+
+  // a pixel of margin covers the anti-aliasing offsets.
+  float left = (float)(block_x*cone_size) - 1;
+  float top = (float)(block_y*cone_size) - 1;
+  float right = (float)min((block_x + 1)*cone_size, width) + 1;
+  float bottom = (float)min((block_y + 1)*cone_size, height) + 1;
+
+  float3 start_point;
+  float3 axis;
+  float3 corner;
+  getPinholeRay(camera_info, (float2)((left + right)/2, (top + bottom)/2), width, height, &start_point, &axis);
+  float cos_angle = 1;
+  getPinholeRay(camera_info, (float2)(left, top), width, height, &start_point, &corner);
+  cos_angle = fmin(cos_angle, dot(corner, axis));
+  getPinholeRay(camera_info, (float2)(right, top), width, height, &start_point, &corner);
+  cos_angle = fmin(cos_angle, dot(corner, axis));
+  getPinholeRay(camera_info, (float2)(right, bottom), width, height, &start_point, &corner);
+  cos_angle = fmin(cos_angle, dot(corner, axis));
+  getPinholeRay(camera_info, (float2)(left, bottom), width, height, &start_point, &corner);
+  cos_angle = fmin(cos_angle, dot(corner, axis));
+  float tan_angle = sqrt(fmax((float)0, 1 - cos_angle*cos_angle))/cos_angle;
+
+  float t = 0;
+  uint iterations = 0;
+  while(t < MAX_DIST(quality_info) && iterations < MAX_ITERATIONS(quality_info)){
+    float dist = distToScene(scene_object_integer_data_buffer,
+                            scene_object_float_data_buffer,
+                            num_scene_objects,
+                            bvh_nodes,
+                            bvh_node_ranges,
+                            bvh_indices,
+                            num_bvh_nodes,
+                            num_unbounded_objects,
+                            cone_apex + axis*t).dist;
+    iterations++;
+
+    float step = (dist - t*tan_angle)/(1 + tan_angle);
+    if (step < SMALLEST_DIST(quality_info)) {
+      break;
+    }
+    t += step;
+  }
+  cone_buffer[get_global_id(0)] = (float2)(t, (float)iterations);
 }
 
 __kernel void rayCast(__global uint* pixel_buffer,
-                  __constant uchar8* scene_object_integer_data_buffer,
-                  __constant float16* scene_object_float_data_buffer,
+                  __global uint* iteration_buffer,
+                  __global const uchar8* scene_object_integer_data_buffer,
+                  __global const float16* scene_object_float_data_buffer,
                   uint num_scene_objects,
-                  float8 camera_info,
+                  __global const float8* bvh_nodes,
+                  __global const uint2* bvh_node_ranges,
+                  __global const uint* bvh_indices,
+                  uint num_bvh_nodes,
+                  uint num_unbounded_objects,
+                  float8 quality_dists,
+                  uint2 quality_counts,
+                  __global const uint* tile_ranges,
+                  __global const uint* tile_objects,
+                  uint tile_size,
+                  __global const float2* cone_buffer,
+                  float3 cone_apex,
+                  uint cone_size,
+                  float16 camera_info,
                   float3 light_pos,
+                  float8 fog_info,
+                  uint god_ray_samples,
+                  uint debug_view,
+                  uint tone_mapping,
+                  float exposure,
+                  uint sample_pattern,
+                  uint samples_per_axis,
                   uint width, 
                   uint height) {
+  struct QualityInfo quality_info = {quality_dists, quality_counts.s0, quality_counts.s1};
   ulong wid = (ulong)width;
   uint y = (uint) (get_global_id(0) / wid);
   uint x = (uint) (get_global_id(0) % wid);
 
-  float scale = CAMERA_SCALE(camera_info);
-  float zoom = CAMERA_FRAME_DIST(camera_info);
-  float3 camera_pos = CAMERA_POS(camera_info);
-  float3 camera_rot = CAMERA_ROTATION(camera_info);
+  // with tile culling the primary rays only evaluate the objects whose bounds
+  // reach into the tile, as a plain list without the hierarchy.
+  __global const uint* primary_indices = bvh_indices;
+  uint num_primary_nodes = num_bvh_nodes;
+  uint num_primary_unbounded_objects = num_unbounded_objects;
+  if (tile_size > 0) {
+    uint tiles_x = (width + tile_size - 1)/tile_size;
+    uint tile = (y/tile_size)*tiles_x + x/tile_size;
+    primary_indices = tile_objects + TILE_OFFSET(tile_ranges, tile);
+    num_primary_nodes = 0;
+    num_primary_unbounded_objects = TILE_COUNT(tile_ranges, tile);
+  }
+
+  // with cone marching the primary rays skip what the cone of their block found
+  // empty. the first pixel of each block also counts the steps of its cone.
+  float4 primary_cone = (float4)(0);
+  uint iterations = 0;
+  if (cone_size > 0) {
+    uint blocks_x = (width + cone_size - 1)/cone_size;
+    float2 cone = cone_buffer[(y/cone_size)*blocks_x + x/cone_size];
+    primary_cone = (float4)(cone_apex, cone.s0);
+    if (x % cone_size == 0 && y % cone_size == 0) {
+      iterations = (uint)cone.s1;
+    }
+  }
 
-  float offx = ((float)x - (float)width/2)/scale;
-  float offy = ((float)height/2 - (float)y)/scale;
+  float3 color = renderPixel(scene_object_integer_data_buffer,
+                            scene_object_float_data_buffer,
+                            num_scene_objects,
+                            bvh_nodes,
+                            bvh_node_ranges,
+                            bvh_indices,
+                            num_bvh_nodes,
+                            num_unbounded_objects,
+                            quality_info,
+                            primary_indices,
+                            num_primary_nodes,
+                            num_primary_unbounded_objects,
+                            primary_cone,
+                            &iterations,
+                            camera_info,
+                            light_pos,
+                            fog_info,
+                            god_ray_samples,
+                            debug_view,
+                            tone_mapping,
+                            exposure,
+                            sample_pattern,
+                            samples_per_axis,
+                            x,
+                            y,
+                            width,
+                            height);
 
-  float3 direction = (float3)(offx,offy,zoom);
-  direction = vecRotate(direction, camera_rot);
-  direction = fast_normalize(direction);
+  pixel_buffer[get_global_id(0)] = packColor(color);
+  iteration_buffer[get_global_id(0)] = iterations;
+}
 
-  float3 start_point = vecRotateAround(camera_pos + (float3)(offx, offy, 0), camera_rot, camera_pos);
+// adds one sample, jittered inside the pixel after the first, to the running
+// sum of each pixel and writes out the average.
+__kernel void progressiveSample(__global uint* pixel_buffer,
+                  __global float4* accumulation_buffer,
+                  __global const uchar8* scene_object_integer_data_buffer,
+                  __global const float16* scene_object_float_data_buffer,
+                  uint num_scene_objects,
+                  __global const float8* bvh_nodes,
+                  __global const uint2* bvh_node_ranges,
+                  __global const uint* bvh_indices,
+                  uint num_bvh_nodes,
+                  uint num_unbounded_objects,
+                  float8 quality_dists,
+                  uint2 quality_counts,
+                  float16 camera_info,
+                  float3 light_pos,
+                  float8 fog_info,
+                  uint god_ray_samples,
+                  uint debug_view,
+                  uint tone_mapping,
+                  float exposure,
+                  uint sample_index,
+                  uint width, 
+                  uint height) {
+  struct QualityInfo quality_info = {quality_dists, quality_counts.s0, quality_counts.s1};
+  ulong wid = (ulong)width;
+  uint y = (uint) (get_global_id(0) / wid);
+  uint x = (uint) (get_global_id(0) % wid);
 
-  uchar3 color = rayCastHelper(scene_object_integer_data_buffer,
-                                      scene_object_float_data_buffer,
-                                      num_scene_objects,
-                                      light_pos,
-                                      start_point,
-                                      direction,
-                                      0);
-  
-  pixel_buffer[get_global_id(0)] = (uint)color.s0 << 16 | (uint)color.s1 << 8 | (uint)color.s2;
+  uint seed = hashUint(get_global_id(0)) ^ hashUint(sample_index);
+  float2 jitter = (float2)(0);
+  if (sample_index > 0) {
+    jitter = hashToFloat2(seed) - (float)0.5;
+  }
+
+  uint iterations = 0;
+  float3 color = tracePixelSample(scene_object_integer_data_buffer,
+                                  scene_object_float_data_buffer,
+                                  num_scene_objects,
+                                  bvh_nodes,
+                                  bvh_node_ranges,
+                                  bvh_indices,
+                                  num_bvh_nodes,
+                                  num_unbounded_objects,
+                                  quality_info,
+                                  bvh_indices,
+                                  num_bvh_nodes,
+                                  num_unbounded_objects,
+                                  (float4)(0),
+                                  &iterations,
+                                  camera_info,
+                                  light_pos,
+                                  fog_info,
+                                  god_ray_samples,
+                                  debug_view,
+                                  (float2)((float)x, (float)y) + jitter,
+                                  hashToFloat2(hashUint(seed)),
+                                  width,
+                                  height);
+  if (debug_view == DEBUG_SHADED) {
+    color = toneMap(color*exposure, tone_mapping);
+  }
+
+  float4 accumulated = (float4)(color, 1);
+  if (sample_index > 0) {
+    accumulated += accumulation_buffer[get_global_id(0)];
+  }
+  accumulation_buffer[get_global_id(0)] = accumulated;
+
+  pixel_buffer[get_global_id(0)] = packColor(accumulated.xyz/accumulated.w);
+}
+
+// adds one path traced sample per pixel to the running sum of each pixel and
+// writes out the average. the random state of every pixel persists between samples.
+__kernel void pathTraceSample(__global uint* pixel_buffer,
+                  __global float4* accumulation_buffer,
+                  __global uint* rng_state_buffer,
+                  __global const uchar8* scene_object_integer_data_buffer,
+                  __global const float16* scene_object_float_data_buffer,
+                  uint num_scene_objects,
+                  __global const float8* bvh_nodes,
+                  __global const uint2* bvh_node_ranges,
+                  __global const uint* bvh_indices,
+                  uint num_bvh_nodes,
+                  uint num_unbounded_objects,
+                  float8 quality_dists,
+                  uint2 quality_counts,
+                  float16 camera_info,
+                  float3 light_pos,
+                  float8 fog_info,
+                  uint tone_mapping,
+                  float exposure,
+                  uint sample_index,
+                  uint max_bounces,
+                  uint width, 
+                  uint height) {
+  struct QualityInfo quality_info = {quality_dists, quality_counts.s0, quality_counts.s1};
+  ulong wid = (ulong)width;
+  uint y = (uint) (get_global_id(0) / wid);
+  uint x = (uint) (get_global_id(0) % wid);
+
+  uint rng = sample_index == 0 ? hashUint(get_global_id(0)) | 1 : rng_state_buffer[get_global_id(0)];
+
+  float2 jitter = (float2)(randomFloat(&rng), randomFloat(&rng)) - (float)0.5;
+  float2 lens_sample = (float2)(randomFloat(&rng), randomFloat(&rng));
+  float3 start_point;
+  float3 direction;
+  float3 color = (float3)(0);
+  if (getCameraRay(camera_info, (float2)((float)x, (float)y) + jitter, lens_sample, width, height, &start_point, &direction)) {
+    color = pathTrace(scene_object_integer_data_buffer,
+                      scene_object_float_data_buffer,
+                      num_scene_objects,
+                      bvh_nodes,
+                      bvh_node_ranges,
+                      bvh_indices,
+                      num_bvh_nodes,
+                      num_unbounded_objects,
+                      quality_info,
+                      light_pos,
+                      fog_info,
+                      start_point,
+                      direction,
+                      max_bounces,
+                      &rng);
+  }
+  rng_state_buffer[get_global_id(0)] = rng;
+
+  // accumulate in linear hdr so the average converges to the radiance before tone mapping
+  float4 accumulated = (float4)(color, 1);
+  if (sample_index > 0) {
+    accumulated += accumulation_buffer[get_global_id(0)];
+  }
+  accumulation_buffer[get_global_id(0)] = accumulated;
+
+  pixel_buffer[get_global_id(0)] = packColor(toneMap(accumulated.xyz/accumulated.w*exposure, tone_mapping));
+}
+
+// second pass of adaptive anti-aliasing, re-renders with a grid pattern only the
+// pixels of the single sample first pass that differ strongly from a neighbour.
+__kernel void adaptiveSupersample(__global uint* pixel_buffer,
+                  __global const uint* first_pass_buffer,
+                  __global const uchar8* scene_object_integer_data_buffer,
+                  __global const float16* scene_object_float_data_buffer,
+                  uint num_scene_objects,
+                  __global const float8* bvh_nodes,
+                  __global const uint2* bvh_node_ranges,
+                  __global const uint* bvh_indices,
+                  uint num_bvh_nodes,
+                  uint num_unbounded_objects,
+                  float8 quality_dists,
+                  uint2 quality_counts,
+                  float16 camera_info,
+                  float3 light_pos,
+                  float8 fog_info,
+                  uint god_ray_samples,
+                  uint debug_view,
+                  uint tone_mapping,
+                  float exposure,
+                  uint samples_per_axis,
+                  float contrast_threshold,
+                  uint width, 
+                  uint height) {
+  struct QualityInfo quality_info = {quality_dists, quality_counts.s0, quality_counts.s1};
+  ulong wid = (ulong)width;
+  uint y = (uint) (get_global_id(0) / wid);
+  uint x = (uint) (get_global_id(0) % wid);
+
+  uint center = first_pass_buffer[get_global_id(0)];
+  float luminance = packedLuminance(center);
+
+  float max_difference = 0;
+  max_difference = fmax(max_difference, fabs(luminance - packedLuminance(first_pass_buffer[y*width + max(x, (uint)1) - 1])));
+  max_difference = fmax(max_difference, fabs(luminance - packedLuminance(first_pass_buffer[y*width + min(x + 1, width - 1)])));
+  max_difference = fmax(max_difference, fabs(luminance - packedLuminance(first_pass_buffer[(max(y, (uint)1) - 1)*width + x])));
+  max_difference = fmax(max_difference, fabs(luminance - packedLuminance(first_pass_buffer[min(y + 1, height - 1)*width + x])));
+
+  if (max_difference <= contrast_threshold) {
+    pixel_buffer[get_global_id(0)] = center;
+    return;
+  }
+
+  uint iterations = 0;
+  float3 color = renderPixel(scene_object_integer_data_buffer,
+                            scene_object_float_data_buffer,
+                            num_scene_objects,
+                            bvh_nodes,
+                            bvh_node_ranges,
+                            bvh_indices,
+                            num_bvh_nodes,
+                            num_unbounded_objects,
+                            quality_info,
+                            bvh_indices,
+                            num_bvh_nodes,
+                            num_unbounded_objects,
+                            (float4)(0),
+                            &iterations,
+                            camera_info,
+                            light_pos,
+                            fog_info,
+                            god_ray_samples,
+                            debug_view,
+                            tone_mapping,
+                            exposure,
+                            AA_GRID,
+                            samples_per_axis,
+                            x,
+                            y,
+                            width,
+                            height);
+
+  pixel_buffer[get_global_id(0)] = packColor(color);
+}
+
+// renders the sample through the center of every pixel like rayCast does, and
+// writes the passes of its primary ray alongside the color. misses are 0 in the
+// passes of the surface and the largest distance in depth. direct and reflection
+// are the light leaving the surface before the fog, its own shading and what it
+// reflects, so the color is both mixed with the fog plus the god rays.
+__kernel void rayCastAovs(__global uint* pixel_buffer,
+                  __global float* depth_buffer,
+                  __global float4* normal_buffer,
+                  __global float4* position_buffer,
+                  __global uint* object_id_buffer,
+                  __global float4* albedo_buffer,
+                  __global float4* direct_buffer,
+                  __global float4* reflection_buffer,
+                  __global const uchar8* scene_object_integer_data_buffer,
+                  __global const float16* scene_object_float_data_buffer,
+                  uint num_scene_objects,
+                  __global const float8* bvh_nodes,
+                  __global const uint2* bvh_node_ranges,
+                  __global const uint* bvh_indices,
+                  uint num_bvh_nodes,
+                  uint num_unbounded_objects,
+                  float8 quality_dists,
+                  uint2 quality_counts,
+                  float16 camera_info,
+                  float3 light_pos,
+                  float8 fog_info,
+                  uint god_ray_samples,
+                  uint tone_mapping,
+                  float exposure,
+                  uint width, 
+                  uint height) {
+  struct QualityInfo quality_info = {quality_dists, quality_counts.s0, quality_counts.s1};
+  ulong wid = (ulong)width;
+  uint y = (uint) (get_global_id(0) / wid);
+  uint x = (uint) (get_global_id(0) % wid);
+
+  float depth = MAX_DIST(quality_info);
+  float3 normal = (float3)(0);
+  float3 position = (float3)(0);
+  uint object_id = 0;
+  float3 albedo = (float3)(0);
+  float3 direct = (float3)(0);
+  float3 reflection = (float3)(0);
+  float3 color = (float3)(0);
+
+  float3 start_point;
+  float3 direction;
+  float2 lens_sample = hashToFloat2(hashUint(hashUint(y*width + x)));
+  if (getCameraRay(camera_info, (float2)((float)x, (float)y), lens_sample, width, height, &start_point, &direction)) {
+    struct ClosePoint d = getPointAtScene(scene_object_integer_data_buffer, 
+                                scene_object_float_data_buffer, 
+                                num_scene_objects, 
+                                bvh_nodes,
+                                bvh_node_ranges,
+                                bvh_indices,
+                                num_bvh_nodes,
+                                num_unbounded_objects,
+                                quality_info,
+                                direction, 
+                                start_point);
+
+    float light = getLight( scene_object_integer_data_buffer, 
+                            scene_object_float_data_buffer, 
+                            num_scene_objects,
+                            bvh_nodes,
+                            bvh_node_ranges,
+                            bvh_indices,
+                            num_bvh_nodes,
+                            num_unbounded_objects,
+                            quality_info,
+                            d.point,
+                            light_pos);
+
+    float3 surface_albedo = UCHAR3_TO_LINEAR(OBJECT_COLOR(scene_object_integer_data_buffer[d.obj_index]));
+    float reflectivity = REFLECTIVITY(scene_object_float_data_buffer[d.obj_index]);
+    float march_dist = d.out_of_bounds ? MAX_DIST(quality_info) : fast_distance(start_point, d.point);
+
+    if (!d.out_of_bounds) {
+      depth = march_dist;
+      normal = getNormal(scene_object_integer_data_buffer,
+                        scene_object_float_data_buffer,
+                        num_scene_objects,
+                        bvh_nodes,
+                        bvh_node_ranges,
+                        bvh_indices,
+                        num_bvh_nodes,
+                        num_unbounded_objects,
+                        quality_info,
+                        d.point);
+      position = d.point;
+      object_id = d.obj_index + 1;
+      albedo = surface_albedo;
+    }
+
+    bool reflects = !d.out_of_bounds && MAX_REFLECTION_DEPTH(quality_info) > 0 && reflectivity > 0;
+    direct = surface_albedo*light*(reflects ? 1 - reflectivity : 1);
+    if (reflects) {
+      uint iterations = 0;
+      reflection = reflectivity*rayCastHelper(scene_object_integer_data_buffer,
+                                              scene_object_float_data_buffer,
+                                              num_scene_objects,
+                                              bvh_nodes,
+                                              bvh_node_ranges,
+                                              bvh_indices,
+                                              num_bvh_nodes,
+                                              num_unbounded_objects,
+                                              quality_info,
+                                              bvh_indices,
+                                              num_bvh_nodes,
+                                              num_unbounded_objects,
+                                              (float4)(0),
+                                              &iterations,
+                                              light_pos,
+                                              fog_info,
+                                              0,
+                                              DEBUG_SHADED,
+                                              d.point + normal*NORMAL_EPSILON(quality_info),
+                                              getReflection(direction, normal),
+                                              1);
+    }
+
+    float fog = 1 - exp(-fogOpticalDepth(fog_info, start_point, direction, march_dist));
+    color = mix(direct + reflection, UCHAR3_TO_LINEAR(FOG_COLOR(fog_info)), fog);
+    if (god_ray_samples > 0) {
+      color += getGodRays(scene_object_integer_data_buffer,
+                          scene_object_float_data_buffer,
+                          num_scene_objects,
+                          bvh_nodes,
+                          bvh_node_ranges,
+                          bvh_indices,
+                          num_bvh_nodes,
+                          num_unbounded_objects,
+                          quality_info,
+                          fog_info,
+                          light_pos,
+                          start_point,
+                          direction,
+                          march_dist,
+                          god_ray_samples);
+    }
+  }
+
+  pixel_buffer[get_global_id(0)] = packColor(toneMap(color*exposure, tone_mapping));
+  depth_buffer[get_global_id(0)] = depth;
+  normal_buffer[get_global_id(0)] = (float4)(normal, 0);
+  position_buffer[get_global_id(0)] = (float4)(position, 0);
+  object_id_buffer[get_global_id(0)] = object_id;
+  albedo_buffer[get_global_id(0)] = (float4)(albedo, 0);
+  direct_buffer[get_global_id(0)] = (float4)(direct, 0);
+  reflection_buffer[get_global_id(0)] = (float4)(reflection, 0);
 }
\ No newline at end of file
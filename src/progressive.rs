@@ -0,0 +1,64 @@
+extern crate ocl;
+
+use ocl::{Buffer, ProQue};
+use ocl::prm::{Float4, Float16, Uint};
+
+use crate::camera::Camera;
+use crate::scene::Scene;
+use crate::render_settings::RenderSettings;
+
+/// device side running sum of the samples of every pixel, the sum restarts
+/// whenever the camera, scene or render settings differ from the last frame.
+/// also holds the per pixel random state of the path tracer.
+pub struct ProgressiveAccumulator {
+  accumulation_buffer: Buffer<Float4>,
+  rng_state_buffer: Buffer<Uint>,
+  sample_count: u32,
+  last_camera: Float16,
+  // the id and version of the last scene.
+  last_scene_version: Option<(u64, u64)>,
+  last_settings: Option<RenderSettings>
+}
+
+impl ProgressiveAccumulator {
+  pub fn new(pro_que: &ProQue) -> Result<Self, ocl::Error> {
+    Ok(ProgressiveAccumulator {accumulation_buffer: pro_que.create_buffer::<Float4>()?,
+                              rng_state_buffer: pro_que.create_buffer::<Uint>()?,
+                              sample_count: 0,
+                              last_camera: Float16::zero(),
+                              last_scene_version: None,
+                              last_settings: None})
+  }
+
+  /// returns the index of the sample to render this frame.
+  pub fn next_sample(&mut self, camera: &Camera, scene: &Scene, settings: &RenderSettings) -> u32 {
+    let camera_data = camera.get_data();
+    let scene_version = Some((scene.get_id(), scene.get_version()));
+    let unchanged = self.last_camera == camera_data
+      && self.last_scene_version == scene_version
+      && self.last_settings.as_ref() == Some(settings);
+
+    if !unchanged {
+      self.sample_count = 0;
+      self.last_camera = camera_data;
+      self.last_scene_version = scene_version;
+      self.last_settings = Some(settings.clone());
+    }
+
+    let sample = self.sample_count;
+    self.sample_count += 1;
+    sample
+  }
+
+  pub fn get_sample_count(&self) -> u32 {
+    self.sample_count
+  }
+
+  pub fn get_buffer(&self) -> &Buffer<Float4> {
+    &self.accumulation_buffer
+  }
+
+  pub fn get_rng_state_buffer(&self) -> &Buffer<Uint> {
+    &self.rng_state_buffer
+  }
+}
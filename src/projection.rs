@@ -0,0 +1,52 @@
+#[derive(Clone, Copy, PartialEq)]
+pub enum Projection {
+  /// rays start on a frame `scale` pixels per unit in front of the camera and
+  /// point away from a focal point `frame_distance` behind the frame.
+  Legacy {frame_distance: f32, scale: f32},
+  /// pinhole camera with the vertical field of view in degrees, rays start on
+  /// the near plane. aspect is width over height, None uses the image's.
+  Perspective {fov: f32, aspect: Option<f32>, near: f32},
+  /// parallel rays along the look direction from a view `height` units tall,
+  /// its width follows the image aspect.
+  Orthographic {height: f32},
+  /// equidistant fisheye covering `fov` degrees across the circle inscribed
+  /// in the image, pixels outside the circle are black.
+  Fisheye {fov: f32},
+  /// full 360 by 180 degree panorama centered on the look direction.
+  Equirectangular
+}
+
+impl Projection {
+  pub fn perspective(fov: f32) -> Self {
+    Projection::Perspective {fov: fov, aspect: None, near: 0.}
+  }
+  /// cycles through the projections with their default parameters.
+  pub fn next(&self) -> Self {
+    match self {
+      Projection::Legacy {..} => Projection::perspective(60.),
+      Projection::Perspective {..} => Projection::Orthographic {height: 20.},
+      Projection::Orthographic {..} => Projection::Fisheye {fov: 180.},
+      Projection::Fisheye {..} => Projection::Equirectangular,
+      Projection::Equirectangular => Projection::Legacy {frame_distance: 100., scale: 20.}
+    }
+  }
+  pub fn get_key(&self) -> u32 {
+    match self {
+      Projection::Legacy {..} => 0,
+      Projection::Perspective {..} => 1,
+      Projection::Orthographic {..} => 2,
+      Projection::Fisheye {..} => 3,
+      Projection::Equirectangular => 4
+    }
+  }
+  /// packed the way the kernel reads them for this projection.
+  pub fn get_parameters(&self) -> (f32, f32, f32) {
+    match *self {
+      Projection::Legacy {frame_distance, scale} => (frame_distance, scale, 0.),
+      Projection::Perspective {fov, aspect, near} => ((fov.to_radians()/2.).tan(), aspect.unwrap_or(0.), near),
+      Projection::Orthographic {height} => (height, 0., 0.),
+      Projection::Fisheye {fov} => (fov.to_radians()/2., 0., 0.),
+      Projection::Equirectangular => (0., 0., 0.)
+    }
+  }
+}
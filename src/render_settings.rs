@@ -0,0 +1,393 @@
+extern crate ocl;
+
+use std::fmt;
+use std::str::FromStr;
+
+use ocl::prm::{Float8, Uint2};
+
+use crate::camera::Camera;
+use crate::stereo::Stereo;
+use crate::error::Error;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ToneMapping {
+  None,
+  Reinhard,
+  Aces,
+  Filmic
+}
+
+impl ToneMapping {
+  pub fn get_key(&self) -> u32 {
+    match self {
+      ToneMapping::None => 0,
+      ToneMapping::Reinhard => 1,
+      ToneMapping::Aces => 2,
+      ToneMapping::Filmic => 3
+    }
+  }
+  pub fn next(&self) -> Self {
+    match self {
+      ToneMapping::None => ToneMapping::Reinhard,
+      ToneMapping::Reinhard => ToneMapping::Aces,
+      ToneMapping::Aces => ToneMapping::Filmic,
+      ToneMapping::Filmic => ToneMapping::None
+    }
+  }
+}
+
+/// none, reinhard, aces or filmic.
+impl FromStr for ToneMapping {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self, Error> {
+    match s {
+      "none" => Ok(ToneMapping::None),
+      "reinhard" => Ok(ToneMapping::Reinhard),
+      "aces" => Ok(ToneMapping::Aces),
+      "filmic" => Ok(ToneMapping::Filmic),
+      _ => Err(Error::Parse(format!("unknown tone mapping {}", s)))
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AntiAliasing {
+  None,
+  /// n by n regular grid of samples per pixel.
+  Grid(u32),
+  /// the four sample rotated grid pattern.
+  RotatedGrid,
+  /// one randomly placed sample in each cell of an n by n grid.
+  Stratified(u32),
+  /// n by n grid, but only on pixels whose single sample luminance differs
+  /// from one of its neighbours by more than the threshold.
+  Adaptive(u32, f32)
+}
+
+impl AntiAliasing {
+  pub fn get_key(&self) -> u32 {
+    match self {
+      AntiAliasing::None => 0,
+      AntiAliasing::Grid(_) => 1,
+      AntiAliasing::RotatedGrid => 2,
+      AntiAliasing::Stratified(_) => 3,
+      AntiAliasing::Adaptive(_, _) => 1
+    }
+  }
+  pub fn samples_per_axis(&self) -> u32 {
+    match self {
+      AntiAliasing::Grid(n) | AntiAliasing::Stratified(n) | AntiAliasing::Adaptive(n, _) => *n,
+      AntiAliasing::None | AntiAliasing::RotatedGrid => 1
+    }
+  }
+  pub fn next(&self) -> Self {
+    match self {
+      AntiAliasing::None => AntiAliasing::Grid(2),
+      AntiAliasing::Grid(_) => AntiAliasing::RotatedGrid,
+      AntiAliasing::RotatedGrid => AntiAliasing::Stratified(2),
+      AntiAliasing::Stratified(_) => AntiAliasing::Adaptive(3, 0.1),
+      AntiAliasing::Adaptive(_, _) => AntiAliasing::None
+    }
+  }
+}
+
+/// none, grid:<n>, rotated-grid, stratified:<n> or adaptive:<n>:<threshold>.
+impl FromStr for AntiAliasing {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self, Error> {
+    let parts: Vec<&str> = s.split(':').collect();
+    let invalid = || Error::Parse(format!("invalid anti-aliasing {}", s));
+    let samples = |i: usize| parts.get(i).and_then(|n| n.parse::<u32>().ok()).filter(|&n| n > 0).ok_or_else(invalid);
+    match (parts[0], parts.len()) {
+      ("none", 1) => Ok(AntiAliasing::None),
+      ("grid", 2) => Ok(AntiAliasing::Grid(samples(1)?)),
+      ("rotated-grid", 1) => Ok(AntiAliasing::RotatedGrid),
+      ("stratified", 2) => Ok(AntiAliasing::Stratified(samples(1)?)),
+      ("adaptive", 3) => {
+        let threshold = parts[2].parse::<f32>().map_err(|_| invalid())?;
+        Ok(AntiAliasing::Adaptive(samples(1)?, threshold))
+      }
+      _ => Err(invalid())
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Integrator {
+  /// deterministic shading from the point light with mirror reflections.
+  Whitted,
+  /// monte carlo global illumination, always accumulated progressively.
+  PathTracing
+}
+
+/// whitted or path.
+impl FromStr for Integrator {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self, Error> {
+    match s {
+      "whitted" => Ok(Integrator::Whitted),
+      "path" => Ok(Integrator::PathTracing),
+      _ => Err(Error::Parse(format!("unknown integrator {}", s)))
+    }
+  }
+}
+
+/// what the primary rays show instead of the shaded color, to find out why a
+/// scene renders wrongly or slowly. every view but shaded uses the whitted kernels.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DebugView {
+  Shaded,
+  /// the surface normal mapped from [-1, 1] to [0, 1] per axis.
+  Normals,
+  /// distance along the ray, white near the camera and black at the largest distance.
+  Depth,
+  /// march steps of the primary ray, blue for few to red for the most allowed.
+  Iterations,
+  /// a color hashed from the index of the object hit.
+  ObjectId,
+  /// white where the light is visible, dark in shadow.
+  Shadow
+}
+
+impl DebugView {
+  pub const ALL: [DebugView; 6] = [DebugView::Shaded, DebugView::Normals, DebugView::Depth,
+                                   DebugView::Iterations, DebugView::ObjectId, DebugView::Shadow];
+
+  pub fn get_key(&self) -> u32 {
+    match self {
+      DebugView::Shaded => 0,
+      DebugView::Normals => 1,
+      DebugView::Depth => 2,
+      DebugView::Iterations => 3,
+      DebugView::ObjectId => 4,
+      DebugView::Shadow => 5
+    }
+  }
+  pub fn get_name(&self) -> &'static str {
+    match self {
+      DebugView::Shaded => "shaded",
+      DebugView::Normals => "normals",
+      DebugView::Depth => "depth",
+      DebugView::Iterations => "iterations",
+      DebugView::ObjectId => "object-id",
+      DebugView::Shadow => "shadow"
+    }
+  }
+}
+
+/// shaded, normals, depth, iterations, object-id or shadow.
+impl FromStr for DebugView {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self, Error> {
+    DebugView::ALL.iter().find(|view| view.get_name() == s).cloned()
+      .ok_or_else(|| Error::Parse(format!("unknown debug view {}", s)))
+  }
+}
+
+/// how the rays step through the scene, everything off is plain sphere tracing.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Marching {
+  /// factor every step is over-relaxed by until one overshoots, in [1, 2).
+  /// 1 takes plain steps.
+  pub relaxation: f32,
+  /// size in pixels of the blocks a cone is marched for in a low resolution
+  /// pass before the primary rays, which then start where their cone hit
+  /// something. none to not cone march.
+  pub cone_size: Option<u32>,
+  /// grow the hit distance with the size of a pixel at the distance marched
+  /// instead of keeping it fixed.
+  pub pixel_footprint: bool
+}
+
+impl Marching {
+  pub const PLAIN: Marching = Marching {relaxation: 1., cone_size: None, pixel_footprint: false};
+  /// the relaxation of the presets next cycles through.
+  pub const RELAXATION: f32 = 1.6;
+  /// the block size of the presets next cycles through.
+  pub const CONE_SIZE: u32 = 8;
+
+  /// adds one strategy after another, then back to plain.
+  pub fn next(&self) -> Self {
+    if *self == Marching::PLAIN {
+      Marching {relaxation: Marching::RELAXATION, ..Marching::PLAIN}
+    } else if self.cone_size.is_none() {
+      Marching {relaxation: Marching::RELAXATION, cone_size: Some(Marching::CONE_SIZE), pixel_footprint: false}
+    } else if !self.pixel_footprint {
+      Marching {relaxation: Marching::RELAXATION, cone_size: Some(Marching::CONE_SIZE), pixel_footprint: true}
+    } else {
+      Marching::PLAIN
+    }
+  }
+  /// the hit distance per unit marched, half the angle between the rays of
+  /// neighbouring pixels.
+  pub fn get_pixel_footprint(&self, camera: &Camera, width: u32, height: u32) -> f32 {
+    if self.pixel_footprint {camera.get_pixel_angle(width, height)/2.} else {0.}
+  }
+}
+
+/// plain, or any of relaxed[:<factor>], cone[:<size>] and footprint joined by +.
+impl FromStr for Marching {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self, Error> {
+    let invalid = || Error::Parse(format!("invalid marching {}", s));
+    let mut marching = Marching::PLAIN;
+    if s == "plain" {
+      return Ok(marching);
+    }
+    for part in s.split('+') {
+      let parts: Vec<&str> = part.split(':').collect();
+      match (parts[0], parts.len()) {
+        ("relaxed", 1) => marching.relaxation = Marching::RELAXATION,
+        ("relaxed", 2) => {
+          marching.relaxation = parts[1].parse::<f32>().ok().filter(|r| (1. ..2.).contains(r)).ok_or_else(invalid)?;
+        }
+        ("cone", 1) => marching.cone_size = Some(Marching::CONE_SIZE),
+        ("cone", 2) => marching.cone_size = Some(parts[1].parse::<u32>().ok().filter(|&n| n > 0).ok_or_else(invalid)?),
+        ("footprint", 1) => marching.pixel_footprint = true,
+        _ => return Err(invalid())
+      }
+    }
+    Ok(marching)
+  }
+}
+
+/// the same form from_str parses.
+impl fmt::Display for Marching {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let mut parts = Vec::new();
+    if self.relaxation != 1. {
+      parts.push(format!("relaxed:{}", self.relaxation));
+    }
+    if let Some(cone_size) = self.cone_size {
+      parts.push(format!("cone:{}", cone_size));
+    }
+    if self.pixel_footprint {
+      parts.push(String::from("footprint"));
+    }
+    if parts.is_empty() {
+      write!(f, "plain")
+    } else {
+      write!(f, "{}", parts.join("+"))
+    }
+  }
+}
+
+/// how closely the rays follow the surfaces and how far they go, trading speed
+/// for detail.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Quality {
+  /// distance to an object a ray counts as hitting it at, also the offset the
+  /// normals are estimated over.
+  pub smallest_dist: f32,
+  /// distance along the normal reflected and shadow rays start off the surface at.
+  pub normal_epsilon: f32,
+  /// steps a ray takes before it gives up.
+  pub max_iterations: u32,
+  /// distance from every object a ray counts as having left the scene at.
+  pub max_dist: f32,
+  /// most mirror reflections a whitted ray follows.
+  pub max_reflection_depth: u32,
+  /// reflectivity over the reflection depth below which reflections stop.
+  pub min_reflection_cutoff: f32
+}
+
+impl Quality {
+  pub const DRAFT: Quality = Quality {smallest_dist: 0.05, normal_epsilon: 0.15, max_iterations: 48, max_dist: 60.,
+                                      max_reflection_depth: 1, min_reflection_cutoff: 0.2};
+  pub const INTERACTIVE: Quality = Quality {smallest_dist: 0.01, normal_epsilon: 0.1, max_iterations: 100, max_dist: 100.,
+                                            max_reflection_depth: 3, min_reflection_cutoff: 0.05};
+  pub const FINAL: Quality = Quality {smallest_dist: 0.001, normal_epsilon: 0.02, max_iterations: 400, max_dist: 300.,
+                                      max_reflection_depth: 8, min_reflection_cutoff: 0.01};
+
+  /// cycles through the presets, anything else goes to draft.
+  pub fn next(&self) -> Self {
+    if *self == Quality::DRAFT {
+      Quality::INTERACTIVE
+    } else if *self == Quality::INTERACTIVE {
+      Quality::FINAL
+    } else {
+      Quality::DRAFT
+    }
+  }
+  pub fn get_name(&self) -> &'static str {
+    if *self == Quality::DRAFT {
+      "draft"
+    } else if *self == Quality::INTERACTIVE {
+      "interactive"
+    } else if *self == Quality::FINAL {
+      "final"
+    } else {
+      "custom"
+    }
+  }
+}
+
+/// draft, interactive or final.
+impl FromStr for Quality {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self, Error> {
+    match s {
+      "draft" => Ok(Quality::DRAFT),
+      "interactive" => Ok(Quality::INTERACTIVE),
+      "final" => Ok(Quality::FINAL),
+      _ => Err(Error::Parse(format!("unknown quality {}", s)))
+    }
+  }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct RenderSettings {
+  /// number of light visibility samples taken along each primary ray for
+  /// single scattering of the point light through the fog, 0 disables it.
+  pub god_ray_samples: u32,
+  pub tone_mapping: ToneMapping,
+  /// linear multiplier applied to the hdr color before tone mapping.
+  pub exposure: f32,
+  pub anti_aliasing: AntiAliasing,
+  /// accumulate jittered samples over frames while nothing changes instead
+  /// of using the anti-aliasing pattern.
+  pub progressive: bool,
+  pub integrator: Integrator,
+  /// longest path the path tracer follows before russian roulette or not.
+  pub max_bounces: u32,
+  /// render a left and a right eye and composite them, not accumulated progressively.
+  pub stereo: Option<Stereo>,
+  /// only evaluate the objects near each point through a bounding volume
+  /// hierarchy instead of every object.
+  pub bvh: bool,
+  /// size in pixels of the screen tiles the primary rays of the whitted
+  /// integrator cull the scene objects for, none to not cull.
+  pub tile_culling: Option<u32>,
+  pub marching: Marching,
+  pub quality: Quality,
+  pub debug_view: DebugView
+}
+
+impl RenderSettings {
+  pub fn new() -> Self {
+    RenderSettings {god_ray_samples: 0, tone_mapping: ToneMapping::Aces, exposure: 1., anti_aliasing: AntiAliasing::None, progressive: false,
+                    integrator: Integrator::Whitted, max_bounces: 8, stereo: None, bvh: true,
+                    tile_culling: None, marching: Marching::PLAIN, quality: Quality::INTERACTIVE,
+                    debug_view: DebugView::Shaded}
+  }
+  /// whether the frames are path traced, the debug views are always whitted.
+  pub fn is_path_traced(&self) -> bool {
+    self.integrator == Integrator::PathTracing && self.debug_view == DebugView::Shaded
+  }
+  /// the distances of the marching and quality settings packed the way the kernels read them.
+  pub fn get_quality_data(&self, camera: &Camera, width: u32, height: u32) -> Float8 {
+    let quality = &self.quality;
+    Float8::new(self.marching.relaxation, self.marching.get_pixel_footprint(camera, width, height),
+                quality.smallest_dist, quality.normal_epsilon, quality.max_dist, quality.min_reflection_cutoff, 0., 0.)
+  }
+  /// the most march steps and reflections, the counts the kernels take next to the quality data.
+  pub fn get_quality_counts(&self) -> Uint2 {
+    Uint2::new(self.quality.max_iterations, self.quality.max_reflection_depth)
+  }
+}
+
+impl Default for RenderSettings {
+  fn default() -> Self {
+    RenderSettings::new()
+  }
+}
@@ -0,0 +1,927 @@
+extern crate ocl;
+
+use std::collections::VecDeque;
+use std::ops::{Add, AddAssign};
+use std::time::{Duration, Instant};
+
+use ocl::{Buffer, Kernel, ProQue, Platform, Device, Queue, Event, OclPrm};
+use ocl::prm::{Uchar8, Uint2, Float2, Float3, Float4, Float8, Float16};
+use ocl::flags::MemFlags;
+
+use crate::camera::Camera;
+use crate::scene::Scene;
+use crate::bvh::Bvh;
+use crate::tile_culling::TileLists;
+use crate::render_settings::{RenderSettings, AntiAliasing};
+use crate::progressive::ProgressiveAccumulator;
+use crate::stereo::Stereo;
+use crate::aov::{Aov, Aovs};
+
+/// source of every kernel the renderer runs.
+pub const KERNEL_SOURCE: &str = include_str!("opencl/kernel.cl");
+
+/// builds the kernels for a device, with one work item per pixel of a `width`
+/// by `height` image.
+pub fn create_pro_que(platform: Platform, device: Device, width: u32, height: u32) -> Result<ProQue, ocl::Error> {
+  ProQue::builder()
+    .src(KERNEL_SOURCE)
+    .platform(platform)
+    .device(device)
+    .dims(width*height)
+    .build()
+}
+
+/// how long each part of a frame took on the host, the device work is waited on
+/// before each part ends.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTimings {
+  /// writing the changed scene objects to the device.
+  pub upload: Duration,
+  /// running the kernels.
+  pub kernel: Duration,
+  /// reading the pixels back.
+  pub readback: Duration
+}
+
+impl FrameTimings {
+  pub fn total(&self) -> Duration {
+    self.upload + self.kernel + self.readback
+  }
+}
+
+impl Add for FrameTimings {
+  type Output = FrameTimings;
+
+  fn add(self, other: FrameTimings) -> FrameTimings {
+    FrameTimings {upload: self.upload + other.upload,
+                  kernel: self.kernel + other.kernel,
+                  readback: self.readback + other.readback}
+  }
+}
+
+impl AddAssign for FrameTimings {
+  fn add_assign(&mut self, other: FrameTimings) {
+    *self = *self + other;
+  }
+}
+
+/// keeps the kernels, the pixel buffers and a copy of the scene on the device
+/// between frames, so a frame only uploads the scene objects that changed since
+/// the last one. rendering a different scene uploads all of it again.
+///
+/// frames are either rendered and read back in one call, or submitted and read
+/// later so the device renders the next frame while the last one is read. the
+/// two pixel buffers let one frame be in flight while another is read.
+pub struct Renderer {
+  pro_que: ProQue,
+  // second queue the submitted frames are read on, so reading one frame does
+  // not wait for the kernels of the next.
+  read_queue: Queue,
+  width: u32,
+  height: u32,
+  pixel_buffers: [Buffer<u32>; 2],
+  // index of the pixel buffer the next submitted frame renders into.
+  next_pixel_buffer: usize,
+  // the single sample render adaptive anti-aliasing starts from.
+  first_pass_buffer: Buffer<u32>,
+  // march steps of the primary rays of each pixel in the last whitted frame.
+  iteration_buffer: Buffer<u32>,
+  scene_object_integer_buffer: Buffer<Uchar8>,
+  scene_object_float_buffer: Buffer<Float16>,
+  // number of objects the scene buffers have room for.
+  scene_capacity: usize,
+  // the id and version of the uploaded scene, none until the first upload.
+  uploaded_version: Option<(u64, u64)>,
+  bvh_node_buffer: Buffer<Float8>,
+  bvh_node_range_buffer: Buffer<Uint2>,
+  bvh_index_buffer: Buffer<u32>,
+  bvh_node_capacity: usize,
+  bvh_index_capacity: usize,
+  // the scene id, objects version and whether the tree was enabled for the uploaded tree.
+  uploaded_bvh: Option<(u64, u64, bool)>,
+  // the data of the last upload, written without blocking so submitting a frame
+  // does not wait for the one before it, and the event of its last write.
+  upload_data: UploadData,
+  upload_event: Option<Event>,
+  // the object lists of the tiles, built every frame from the camera.
+  tile_range_buffer: Buffer<u32>,
+  tile_object_buffer: Buffer<u32>,
+  tile_range_capacity: usize,
+  tile_object_capacity: usize,
+  // the free distance and steps of the cone of each block, from the cone pass.
+  cone_buffer: Buffer<Float2>,
+  cone_capacity: usize,
+  cone_kernel: Kernel,
+  ray_cast_kernel: Kernel,
+  adaptive_kernel: Kernel,
+  progressive_kernel: Kernel,
+  path_trace_kernel: Kernel,
+  // none until the first frame with passes, most renders never need them.
+  aov_passes: Option<AovPasses>,
+  accumulator: ProgressiveAccumulator,
+  // submitted frames not yet read, oldest first.
+  in_flight: VecDeque<InFlightFrame>,
+  output: Vec<u32>,
+  timings: FrameTimings
+}
+
+// host copies of the data written to the device, which have to live until the
+// writes complete.
+#[derive(Default)]
+struct UploadData {
+  objects_integer: Vec<Uchar8>,
+  objects_float: Vec<Float16>,
+  bvh_nodes: Vec<Float8>,
+  bvh_node_ranges: Vec<Uint2>,
+  bvh_indices: Vec<u32>,
+  tile_ranges: Vec<u32>,
+  tile_objects: Vec<u32>
+}
+
+// the buffers the passes are written to and the kernel writing them with the color.
+struct AovPasses {
+  depth_buffer: Buffer<f32>,
+  normal_buffer: Buffer<Float4>,
+  position_buffer: Buffer<Float4>,
+  object_id_buffer: Buffer<u32>,
+  albedo_buffer: Buffer<Float4>,
+  direct_buffer: Buffer<Float4>,
+  reflection_buffer: Buffer<Float4>,
+  kernel: Kernel
+}
+
+enum InFlightFrame {
+  // rendering into the pixel buffer with this index, done when the event completes.
+  Device(usize, Event),
+  // stereo frames are composited on the host when submitted, they wait in output.
+  Host
+}
+
+impl Renderer {
+  pub fn new(platform: Platform, device: Device, width: u32, height: u32) -> Result<Self, ocl::Error> {
+    let pro_que = create_pro_que(platform, device, width, height)?;
+    let read_queue = Queue::new(pro_que.context(), pro_que.device(), None)?;
+
+    let pixel_buffers = [create_pixel_buffer(&pro_que)?, create_pixel_buffer(&pro_que)?];
+    let first_pass_buffer = pro_que.create_buffer::<u32>()?;
+    let iteration_buffer = pro_que.create_buffer::<u32>()?;
+    let scene_capacity = 1;
+    let (scene_object_integer_buffer, scene_object_float_buffer) = create_scene_buffers(&pro_que, scene_capacity)?;
+    let (bvh_node_capacity, bvh_index_capacity) = (1, 1);
+    let (bvh_node_buffer, bvh_node_range_buffer, bvh_index_buffer) = create_bvh_buffers(&pro_que, bvh_node_capacity, bvh_index_capacity)?;
+    let (tile_range_capacity, tile_object_capacity) = (1, 1);
+    let tile_range_buffer = create_index_buffer(&pro_que, tile_range_capacity)?;
+    let tile_object_buffer = create_index_buffer(&pro_que, tile_object_capacity)?;
+    let cone_capacity = 1;
+    let cone_buffer = create_cone_buffer(&pro_que, cone_capacity)?;
+    let accumulator = ProgressiveAccumulator::new(&pro_que)?;
+
+    // the arguments that change between frames are named and set before each run.
+    let cone_kernel = pro_que.kernel_builder("coneMarch")
+    .arg_named("cone_buffer", &cone_buffer)
+    .arg_named("scene_object_integer_buffer", &scene_object_integer_buffer)
+    .arg_named("scene_object_float_buffer", &scene_object_float_buffer)
+    .arg_named("num_scene_objects", 0u32)
+    .arg_named("bvh_nodes", &bvh_node_buffer)
+    .arg_named("bvh_node_ranges", &bvh_node_range_buffer)
+    .arg_named("bvh_indices", &bvh_index_buffer)
+    .arg_named("num_bvh_nodes", 0u32)
+    .arg_named("num_unbounded_objects", 0u32)
+    .arg_named("quality_dists", Float8::zero())
+    .arg_named("quality_counts", Uint2::zero())
+    .arg_named("camera", Float16::zero())
+    .arg_named("cone_apex", Float3::zero())
+    .arg_named("cone_size", 1u32)
+    .arg(width)
+    .arg(height)
+    .build()?;
+
+    let ray_cast_kernel = pro_que.kernel_builder("rayCast")
+    .arg_named("pixel_buffer", &pixel_buffers[0])
+    .arg(&iteration_buffer)
+    .arg_named("scene_object_integer_buffer", &scene_object_integer_buffer)
+    .arg_named("scene_object_float_buffer", &scene_object_float_buffer)
+    .arg_named("num_scene_objects", 0u32)
+    .arg_named("bvh_nodes", &bvh_node_buffer)
+    .arg_named("bvh_node_ranges", &bvh_node_range_buffer)
+    .arg_named("bvh_indices", &bvh_index_buffer)
+    .arg_named("num_bvh_nodes", 0u32)
+    .arg_named("num_unbounded_objects", 0u32)
+    .arg_named("quality_dists", Float8::zero())
+    .arg_named("quality_counts", Uint2::zero())
+    .arg_named("tile_ranges", &tile_range_buffer)
+    .arg_named("tile_objects", &tile_object_buffer)
+    .arg_named("tile_size", 0u32)
+    .arg_named("cone_buffer", &cone_buffer)
+    .arg_named("cone_apex", Float3::zero())
+    .arg_named("cone_size", 0u32)
+    .arg_named("camera", Float16::zero())
+    .arg_named("light", Float3::zero())
+    .arg_named("fog", Float8::zero())
+    .arg_named("god_ray_samples", 0u32)
+    .arg_named("debug_view", 0u32)
+    .arg_named("tone_mapping", 0u32)
+    .arg_named("exposure", 0f32)
+    .arg_named("sample_pattern", 0u32)
+    .arg_named("samples_per_axis", 0u32)
+    .arg(width)
+    .arg(height)
+    .build()?;
+
+    let adaptive_kernel = pro_que.kernel_builder("adaptiveSupersample")
+    .arg_named("pixel_buffer", &pixel_buffers[0])
+    .arg(&first_pass_buffer)
+    .arg_named("scene_object_integer_buffer", &scene_object_integer_buffer)
+    .arg_named("scene_object_float_buffer", &scene_object_float_buffer)
+    .arg_named("num_scene_objects", 0u32)
+    .arg_named("bvh_nodes", &bvh_node_buffer)
+    .arg_named("bvh_node_ranges", &bvh_node_range_buffer)
+    .arg_named("bvh_indices", &bvh_index_buffer)
+    .arg_named("num_bvh_nodes", 0u32)
+    .arg_named("num_unbounded_objects", 0u32)
+    .arg_named("quality_dists", Float8::zero())
+    .arg_named("quality_counts", Uint2::zero())
+    .arg_named("camera", Float16::zero())
+    .arg_named("light", Float3::zero())
+    .arg_named("fog", Float8::zero())
+    .arg_named("god_ray_samples", 0u32)
+    .arg_named("debug_view", 0u32)
+    .arg_named("tone_mapping", 0u32)
+    .arg_named("exposure", 0f32)
+    .arg_named("samples_per_axis", 0u32)
+    .arg_named("contrast_threshold", 0f32)
+    .arg(width)
+    .arg(height)
+    .build()?;
+
+    let progressive_kernel = pro_que.kernel_builder("progressiveSample")
+    .arg_named("pixel_buffer", &pixel_buffers[0])
+    .arg(accumulator.get_buffer())
+    .arg_named("scene_object_integer_buffer", &scene_object_integer_buffer)
+    .arg_named("scene_object_float_buffer", &scene_object_float_buffer)
+    .arg_named("num_scene_objects", 0u32)
+    .arg_named("bvh_nodes", &bvh_node_buffer)
+    .arg_named("bvh_node_ranges", &bvh_node_range_buffer)
+    .arg_named("bvh_indices", &bvh_index_buffer)
+    .arg_named("num_bvh_nodes", 0u32)
+    .arg_named("num_unbounded_objects", 0u32)
+    .arg_named("quality_dists", Float8::zero())
+    .arg_named("quality_counts", Uint2::zero())
+    .arg_named("camera", Float16::zero())
+    .arg_named("light", Float3::zero())
+    .arg_named("fog", Float8::zero())
+    .arg_named("god_ray_samples", 0u32)
+    .arg_named("debug_view", 0u32)
+    .arg_named("tone_mapping", 0u32)
+    .arg_named("exposure", 0f32)
+    .arg_named("sample_index", 0u32)
+    .arg(width)
+    .arg(height)
+    .build()?;
+
+    let path_trace_kernel = pro_que.kernel_builder("pathTraceSample")
+    .arg_named("pixel_buffer", &pixel_buffers[0])
+    .arg(accumulator.get_buffer())
+    .arg(accumulator.get_rng_state_buffer())
+    .arg_named("scene_object_integer_buffer", &scene_object_integer_buffer)
+    .arg_named("scene_object_float_buffer", &scene_object_float_buffer)
+    .arg_named("num_scene_objects", 0u32)
+    .arg_named("bvh_nodes", &bvh_node_buffer)
+    .arg_named("bvh_node_ranges", &bvh_node_range_buffer)
+    .arg_named("bvh_indices", &bvh_index_buffer)
+    .arg_named("num_bvh_nodes", 0u32)
+    .arg_named("num_unbounded_objects", 0u32)
+    .arg_named("quality_dists", Float8::zero())
+    .arg_named("quality_counts", Uint2::zero())
+    .arg_named("camera", Float16::zero())
+    .arg_named("light", Float3::zero())
+    .arg_named("fog", Float8::zero())
+    .arg_named("tone_mapping", 0u32)
+    .arg_named("exposure", 0f32)
+    .arg_named("sample_index", 0u32)
+    .arg_named("max_bounces", 0u32)
+    .arg(width)
+    .arg(height)
+    .build()?;
+
+    Ok(Renderer {pro_que: pro_que,
+                 read_queue: read_queue,
+                 width: width,
+                 height: height,
+                 pixel_buffers: pixel_buffers,
+                 next_pixel_buffer: 0,
+                 first_pass_buffer: first_pass_buffer,
+                 iteration_buffer: iteration_buffer,
+                 scene_object_integer_buffer: scene_object_integer_buffer,
+                 scene_object_float_buffer: scene_object_float_buffer,
+                 scene_capacity: scene_capacity,
+                 uploaded_version: None,
+                 bvh_node_buffer: bvh_node_buffer,
+                 bvh_node_range_buffer: bvh_node_range_buffer,
+                 bvh_index_buffer: bvh_index_buffer,
+                 bvh_node_capacity: bvh_node_capacity,
+                 bvh_index_capacity: bvh_index_capacity,
+                 uploaded_bvh: None,
+                 upload_data: UploadData::default(),
+                 upload_event: None,
+                 tile_range_buffer: tile_range_buffer,
+                 tile_object_buffer: tile_object_buffer,
+                 tile_range_capacity: tile_range_capacity,
+                 tile_object_capacity: tile_object_capacity,
+                 cone_buffer: cone_buffer,
+                 cone_capacity: cone_capacity,
+                 cone_kernel: cone_kernel,
+                 ray_cast_kernel: ray_cast_kernel,
+                 adaptive_kernel: adaptive_kernel,
+                 progressive_kernel: progressive_kernel,
+                 path_trace_kernel: path_trace_kernel,
+                 aov_passes: None,
+                 accumulator: accumulator,
+                 in_flight: VecDeque::with_capacity(2),
+                 output: vec![0; (width*height) as usize],
+                 timings: FrameTimings::default()})
+  }
+
+  pub fn get_width(&self) -> u32 {
+    self.width
+  }
+
+  pub fn get_height(&self) -> u32 {
+    self.height
+  }
+
+  /// timings of the last frame, both eyes together for stereo frames. for
+  /// submitted frames the kernel time is how long reading waited for the kernels.
+  pub fn get_timings(&self) -> FrameTimings {
+    self.timings
+  }
+
+  /// samples accumulated by the progressive and path traced frames so far.
+  pub fn get_sample_count(&self) -> u32 {
+    self.accumulator.get_sample_count()
+  }
+
+  /// march steps the primary rays of each pixel took in the last frame rendered
+  /// with the whitted integrator, including each block's share of the cone pass
+  /// on its first pixel. the steps of adaptive anti-aliasing's second pass are not counted.
+  pub fn read_iterations(&mut self) -> Result<Vec<u32>, ocl::Error> {
+    self.finish_in_flight()?;
+    let mut iterations = vec![0; (self.width*self.height) as usize];
+    self.iteration_buffer.read(&mut iterations).enq()?;
+    Ok(iterations)
+  }
+
+  /// number of submitted frames not yet read.
+  pub fn get_frames_in_flight(&self) -> usize {
+    self.in_flight.len()
+  }
+
+  /// whether read_frame would return the oldest frame in flight without waiting
+  /// for the device, which stereo frames are composited on the host to be.
+  pub fn is_frame_ready(&self) -> bool {
+    matches!(self.in_flight.front(), Some(InFlightFrame::Host))
+  }
+
+  /// renders one frame with the whitted integrator, as packed 0x00RRGGBB pixels
+  /// row by row from the top.
+  pub fn render_frame(&mut self, camera: &Camera, scene: &Scene, settings: &RenderSettings) -> Result<&[u32], ocl::Error> {
+    self.start_sync_frame(camera, scene, settings)?;
+    let start = Instant::now();
+    let event = self.enqueue_ray_cast(camera, scene, settings, 0)?;
+    self.finish_sync_frame(start, event)
+  }
+
+  /// renders one frame with the whitted integrator and a sample through the center
+  /// of each pixel, along with every pass of its primary rays. anti-aliasing,
+  /// tile culling and cone marching are not used.
+  pub fn render_aovs(&mut self, camera: &Camera, scene: &Scene, settings: &RenderSettings) -> Result<Aovs, ocl::Error> {
+    if self.aov_passes.is_none() {
+      self.aov_passes = Some(self.create_aov_passes()?);
+      // the new kernel still needs the tree's counts.
+      self.uploaded_bvh = None;
+    }
+
+    self.start_sync_frame(camera, scene, settings)?;
+    let start = Instant::now();
+    let mut event = Event::empty();
+    {
+      let kernel = &self.aov_passes.as_ref().unwrap().kernel;
+      set_frame_args(kernel, camera, scene, settings, self.width, self.height)?;
+      kernel.set_arg("pixel_buffer", &self.pixel_buffers[0])?;
+      kernel.set_arg("god_ray_samples", settings.god_ray_samples)?;
+      unsafe {
+        kernel.cmd().enew(&mut event).enq()?;
+      }
+    }
+    let color = self.finish_sync_frame(start, event)?.to_vec();
+
+    let aov_passes = self.aov_passes.as_ref().unwrap();
+    let len = (self.width*self.height) as usize;
+    let mut passes = Vec::with_capacity(Aov::ALL.len());
+    for aov in Aov::ALL.iter() {
+      let pass = match aov {
+        Aov::Depth => {
+          let mut depth = vec![0.; len];
+          aov_passes.depth_buffer.read(&mut depth).enq()?;
+          depth
+        }
+        Aov::ObjectId => {
+          let mut object_ids = vec![0u32; len];
+          aov_passes.object_id_buffer.read(&mut object_ids).enq()?;
+          object_ids.into_iter().map(|id| id as f32).collect()
+        }
+        Aov::Normal => read_float3_pass(&aov_passes.normal_buffer, len)?,
+        Aov::Position => read_float3_pass(&aov_passes.position_buffer, len)?,
+        Aov::Albedo => read_float3_pass(&aov_passes.albedo_buffer, len)?,
+        Aov::Direct => read_float3_pass(&aov_passes.direct_buffer, len)?,
+        Aov::Reflection => read_float3_pass(&aov_passes.reflection_buffer, len)?
+      };
+      passes.push(pass);
+    }
+    Ok(Aovs::new(self.width, self.height, settings.quality.max_dist, color, passes))
+  }
+
+  /// adds one jittered sample per pixel to the accumulator and returns the running average.
+  pub fn render_progressive_frame(&mut self, camera: &Camera, scene: &Scene, settings: &RenderSettings) -> Result<&[u32], ocl::Error> {
+    self.start_sync_frame(camera, scene, settings)?;
+    let start = Instant::now();
+    let event = self.enqueue_progressive(camera, scene, settings, 0)?;
+    self.finish_sync_frame(start, event)
+  }
+
+  /// adds one path traced sample per pixel to the accumulator and returns the running average.
+  pub fn render_path_traced_frame(&mut self, camera: &Camera, scene: &Scene, settings: &RenderSettings) -> Result<&[u32], ocl::Error> {
+    self.start_sync_frame(camera, scene, settings)?;
+    let start = Instant::now();
+    let event = self.enqueue_path_trace(camera, scene, settings, 0)?;
+    self.finish_sync_frame(start, event)
+  }
+
+  /// renders both eyes with the whitted integrator and combines them in the stereo layout.
+  pub fn render_stereo_frame(&mut self, camera: &Camera, scene: &Scene, settings: &RenderSettings, stereo: &Stereo) -> Result<&[u32], ocl::Error> {
+    let (left_camera, right_camera) = stereo.get_eyes(camera);
+
+    self.render_frame(&left_camera, scene, settings)?;
+    let left = self.output.clone();
+    let left_timings = self.timings;
+
+    self.render_frame(&right_camera, scene, settings)?;
+    self.timings += left_timings;
+
+    self.output = stereo.composite(&left, &self.output, self.width as usize, self.height as usize);
+    Ok(&self.output)
+  }
+
+  /// renders with whichever of the functions above the settings ask for.
+  pub fn render(&mut self, camera: &Camera, scene: &Scene, settings: &RenderSettings) -> Result<&[u32], ocl::Error> {
+    if let Some(stereo) = settings.stereo {
+      self.render_stereo_frame(camera, scene, settings, &stereo)
+    } else if settings.is_path_traced() {
+      self.render_path_traced_frame(camera, scene, settings)
+    } else if settings.progressive {
+      self.render_progressive_frame(camera, scene, settings)
+    } else {
+      self.render_frame(camera, scene, settings)
+    }
+  }
+
+  /// starts rendering a frame the way render would without waiting for it, the
+  /// frame is collected with read_frame. with two frames already in flight the
+  /// oldest is waited for and dropped first.
+  pub fn submit(&mut self, camera: &Camera, scene: &Scene, settings: &RenderSettings) -> Result<(), ocl::Error> {
+    if self.in_flight.len() == 2 {
+      if let Some(InFlightFrame::Device(_, event)) = self.in_flight.pop_front() {
+        event.wait_for()?;
+      }
+    }
+
+    if let Some(stereo) = settings.stereo {
+      self.finish_in_flight()?;
+      self.render_stereo_frame(camera, scene, settings, &stereo)?;
+      self.in_flight.push_back(InFlightFrame::Host);
+      return Ok(());
+    }
+
+    self.timings.upload = Duration::default();
+    self.upload_scene(camera, scene, settings)?;
+
+    let pixel_buffer = self.next_pixel_buffer;
+    let event = if settings.is_path_traced() {
+      self.enqueue_path_trace(camera, scene, settings, pixel_buffer)?
+    } else if settings.progressive {
+      self.enqueue_progressive(camera, scene, settings, pixel_buffer)?
+    } else {
+      self.enqueue_ray_cast(camera, scene, settings, pixel_buffer)?
+    };
+    self.pro_que.queue().flush()?;
+
+    self.in_flight.push_back(InFlightFrame::Device(pixel_buffer, event));
+    self.next_pixel_buffer = 1 - pixel_buffer;
+    Ok(())
+  }
+
+  /// waits for the oldest submitted frame and copies it into pixels through a
+  /// mapping of its pixel buffer. returns false when no frame is in flight.
+  pub fn read_frame(&mut self, pixels: &mut [u32]) -> Result<bool, ocl::Error> {
+    let frame = match self.in_flight.pop_front() {
+      Some(frame) => frame,
+      None => return Ok(false)
+    };
+
+    match frame {
+      InFlightFrame::Device(pixel_buffer, event) => {
+        let start = Instant::now();
+        event.wait_for()?;
+        self.timings.kernel = start.elapsed();
+
+        let start = Instant::now();
+        let mut mapped = unsafe {
+          self.pixel_buffers[pixel_buffer].map()
+            .queue(&self.read_queue)
+            .read()
+            .enq()?
+        };
+        pixels.copy_from_slice(&mapped);
+        // the next frame rendering into the buffer is enqueued on the other
+        // queue, it must not start while the buffer is still mapped.
+        let mut unmap_event = Event::empty();
+        mapped.unmap().queue(&self.read_queue).enew(&mut unmap_event).enq()?;
+        unmap_event.wait_for()?;
+        self.timings.readback = start.elapsed();
+      }
+      InFlightFrame::Host => pixels.copy_from_slice(&self.output)
+    }
+    Ok(true)
+  }
+
+  // waits for and drops the frames in flight, they share pixel buffers with the
+  // frames rendered in one call.
+  fn finish_in_flight(&mut self) -> Result<(), ocl::Error> {
+    if !self.in_flight.is_empty() {
+      self.pro_que.queue().finish()?;
+      self.in_flight.clear();
+    }
+    Ok(())
+  }
+
+  fn start_sync_frame(&mut self, camera: &Camera, scene: &Scene, settings: &RenderSettings) -> Result<(), ocl::Error> {
+    self.finish_in_flight()?;
+    self.timings = FrameTimings::default();
+    self.upload_scene(camera, scene, settings)
+  }
+
+  // waits for the event of the frame's last kernel and reads the first pixel buffer.
+  fn finish_sync_frame(&mut self, start: Instant, event: Event) -> Result<&[u32], ocl::Error> {
+    event.wait_for()?;
+    self.timings.kernel = start.elapsed();
+
+    let start = Instant::now();
+    self.pixel_buffers[0].read(&mut self.output).enq()?;
+    self.timings.readback = start.elapsed();
+    Ok(&self.output)
+  }
+
+  // enqueues the whitted kernels rendering into the pixel buffer with the index,
+  // returns the event of the last one.
+  fn enqueue_ray_cast(&mut self, camera: &Camera, scene: &Scene, settings: &RenderSettings, pixel_buffer: usize) -> Result<Event, ocl::Error> {
+    // adaptive anti-aliasing starts from a single sample render
+    let first_pass_anti_aliasing = match settings.anti_aliasing {
+      AntiAliasing::Adaptive(_, _) => AntiAliasing::None,
+      anti_aliasing => anti_aliasing
+    };
+    let first_pass_buffer = match settings.anti_aliasing {
+      AntiAliasing::Adaptive(_, _) => &self.first_pass_buffer,
+      _ => &self.pixel_buffers[pixel_buffer]
+    };
+
+    // cone marching needs every primary ray to come from one apex.
+    let cone = match (settings.marching.cone_size, camera.get_frustum_apex()) {
+      (Some(cone_size), Some(apex)) => Some((cone_size.max(1), apex)),
+      _ => None
+    };
+    match cone {
+      Some((cone_size, apex)) => {
+        let blocks = (((self.width + cone_size - 1)/cone_size)*((self.height + cone_size - 1)/cone_size)) as usize;
+        if blocks > self.cone_capacity {
+          self.cone_capacity = grow_capacity(self.cone_capacity, blocks);
+          self.cone_buffer = create_cone_buffer(&self.pro_que, self.cone_capacity)?;
+          self.cone_kernel.set_arg("cone_buffer", &self.cone_buffer)?;
+          self.ray_cast_kernel.set_arg("cone_buffer", &self.cone_buffer)?;
+        }
+        self.cone_kernel.set_arg("num_scene_objects", scene.len() as u32)?;
+        self.cone_kernel.set_arg("camera", camera.get_data())?;
+        self.cone_kernel.set_arg("quality_dists", settings.get_quality_data(camera, self.width, self.height))?;
+        self.cone_kernel.set_arg("quality_counts", settings.get_quality_counts())?;
+        self.cone_kernel.set_arg("cone_apex", Float3::from(apex))?;
+        self.cone_kernel.set_arg("cone_size", cone_size)?;
+        unsafe {
+          self.cone_kernel.cmd().global_work_size(blocks).enq()?;
+        }
+        self.ray_cast_kernel.set_arg("cone_apex", Float3::from(apex))?;
+        self.ray_cast_kernel.set_arg("cone_size", cone_size)?;
+      }
+      None => self.ray_cast_kernel.set_arg("cone_size", 0u32)?
+    }
+
+    set_frame_args(&self.ray_cast_kernel, camera, scene, settings, self.width, self.height)?;
+    self.ray_cast_kernel.set_arg("pixel_buffer", first_pass_buffer)?;
+    self.ray_cast_kernel.set_arg("god_ray_samples", settings.god_ray_samples)?;
+    self.ray_cast_kernel.set_arg("debug_view", settings.debug_view.get_key())?;
+    self.ray_cast_kernel.set_arg("sample_pattern", first_pass_anti_aliasing.get_key())?;
+    self.ray_cast_kernel.set_arg("samples_per_axis", first_pass_anti_aliasing.samples_per_axis())?;
+    let mut event = Event::empty();
+    unsafe {
+      self.ray_cast_kernel.cmd().enew(&mut event).enq()?;
+    }
+
+    if let AntiAliasing::Adaptive(samples_per_axis, contrast_threshold) = settings.anti_aliasing {
+      set_frame_args(&self.adaptive_kernel, camera, scene, settings, self.width, self.height)?;
+      self.adaptive_kernel.set_arg("pixel_buffer", &self.pixel_buffers[pixel_buffer])?;
+      self.adaptive_kernel.set_arg("god_ray_samples", settings.god_ray_samples)?;
+      self.adaptive_kernel.set_arg("debug_view", settings.debug_view.get_key())?;
+      self.adaptive_kernel.set_arg("samples_per_axis", samples_per_axis)?;
+      self.adaptive_kernel.set_arg("contrast_threshold", contrast_threshold)?;
+      event = Event::empty();
+      unsafe {
+        self.adaptive_kernel.cmd().enew(&mut event).enq()?;
+      }
+    }
+    Ok(event)
+  }
+
+  fn enqueue_progressive(&mut self, camera: &Camera, scene: &Scene, settings: &RenderSettings, pixel_buffer: usize) -> Result<Event, ocl::Error> {
+    let sample_index = self.accumulator.next_sample(camera, scene, settings);
+    set_frame_args(&self.progressive_kernel, camera, scene, settings, self.width, self.height)?;
+    self.progressive_kernel.set_arg("pixel_buffer", &self.pixel_buffers[pixel_buffer])?;
+    self.progressive_kernel.set_arg("god_ray_samples", settings.god_ray_samples)?;
+    self.progressive_kernel.set_arg("debug_view", settings.debug_view.get_key())?;
+    self.progressive_kernel.set_arg("sample_index", sample_index)?;
+    let mut event = Event::empty();
+    unsafe {
+      self.progressive_kernel.cmd().enew(&mut event).enq()?;
+    }
+    Ok(event)
+  }
+
+  fn enqueue_path_trace(&mut self, camera: &Camera, scene: &Scene, settings: &RenderSettings, pixel_buffer: usize) -> Result<Event, ocl::Error> {
+    let sample_index = self.accumulator.next_sample(camera, scene, settings);
+    set_frame_args(&self.path_trace_kernel, camera, scene, settings, self.width, self.height)?;
+    self.path_trace_kernel.set_arg("pixel_buffer", &self.pixel_buffers[pixel_buffer])?;
+    self.path_trace_kernel.set_arg("sample_index", sample_index)?;
+    self.path_trace_kernel.set_arg("max_bounces", settings.max_bounces)?;
+    let mut event = Event::empty();
+    unsafe {
+      self.path_trace_kernel.cmd().enew(&mut event).enq()?;
+    }
+    Ok(event)
+  }
+
+  // writes the objects changed since the last upload, growing the scene buffers
+  // and uploading everything when the scene no longer fits. the bounding volume
+  // hierarchy depends on every object, so it is rebuilt whole when any changes.
+  // the tile lists depend on the camera and are rebuilt every frame. the writes
+  // don't block, the queue runs them in order before the kernels.
+  fn upload_scene(&mut self, camera: &Camera, scene: &Scene, settings: &RenderSettings) -> Result<(), ocl::Error> {
+    let start = Instant::now();
+
+    // the data of the last upload is replaced below, so its writes have to be done.
+    // they ran before the last frame's kernels, so this rarely waits.
+    if let Some(event) = self.upload_event.take() {
+      event.wait_for()?;
+    }
+
+    let range = if scene.len() > self.scene_capacity {
+      self.scene_capacity = grow_capacity(self.scene_capacity, scene.len());
+      let (integer_buffer, float_buffer) = create_scene_buffers(&self.pro_que, self.scene_capacity)?;
+      for kernel in &self.get_kernels() {
+        kernel.set_arg("scene_object_integer_buffer", &integer_buffer)?;
+        kernel.set_arg("scene_object_float_buffer", &float_buffer)?;
+      }
+      self.scene_object_integer_buffer = integer_buffer;
+      self.scene_object_float_buffer = float_buffer;
+      Some(0..scene.len())
+    } else {
+      match self.uploaded_version {
+        Some((id, version)) if id == scene.get_id() => scene.get_changed_range(version),
+        _ => Some(0..scene.len())
+      }
+    };
+
+    if let Some(range) = range {
+      if !range.is_empty() {
+        let (objects_integer_data, objects_float_data) = scene.get_ocl_data(range.clone());
+        self.upload_data.objects_integer = objects_integer_data;
+        self.upload_data.objects_float = objects_float_data;
+        self.upload_event = Some(unsafe {write_without_blocking(&self.scene_object_integer_buffer, &self.upload_data.objects_integer, range.start)?});
+        self.upload_event = Some(unsafe {write_without_blocking(&self.scene_object_float_buffer, &self.upload_data.objects_float, range.start)?});
+      }
+    }
+    self.uploaded_version = Some((scene.get_id(), scene.get_version()));
+
+    let bvh_version = (scene.get_id(), scene.get_objects_version(), settings.bvh);
+    if self.uploaded_bvh != Some(bvh_version) {
+      let bvh = if settings.bvh {scene.build_bvh()} else {Bvh::none(scene.len())};
+      self.upload_data.bvh_nodes = bvh.get_node_bounds();
+      self.upload_data.bvh_node_ranges = bvh.get_node_ranges();
+      self.upload_data.bvh_indices = bvh.get_indices().to_vec();
+      let (node_len, index_len) = (self.upload_data.bvh_nodes.len(), self.upload_data.bvh_indices.len());
+
+      if node_len > self.bvh_node_capacity || index_len > self.bvh_index_capacity {
+        self.bvh_node_capacity = grow_capacity(self.bvh_node_capacity, node_len);
+        self.bvh_index_capacity = grow_capacity(self.bvh_index_capacity, index_len);
+        let (node_buffer, node_range_buffer, index_buffer) = create_bvh_buffers(&self.pro_que, self.bvh_node_capacity, self.bvh_index_capacity)?;
+        for kernel in &self.get_kernels() {
+          kernel.set_arg("bvh_nodes", &node_buffer)?;
+          kernel.set_arg("bvh_node_ranges", &node_range_buffer)?;
+          kernel.set_arg("bvh_indices", &index_buffer)?;
+        }
+        self.bvh_node_buffer = node_buffer;
+        self.bvh_node_range_buffer = node_range_buffer;
+        self.bvh_index_buffer = index_buffer;
+      }
+
+      if node_len > 0 {
+        self.upload_event = Some(unsafe {write_without_blocking(&self.bvh_node_buffer, &self.upload_data.bvh_nodes, 0)?});
+        self.upload_event = Some(unsafe {write_without_blocking(&self.bvh_node_range_buffer, &self.upload_data.bvh_node_ranges, 0)?});
+      }
+      if index_len > 0 {
+        self.upload_event = Some(unsafe {write_without_blocking(&self.bvh_index_buffer, &self.upload_data.bvh_indices, 0)?});
+      }
+      for kernel in &self.get_kernels() {
+        kernel.set_arg("num_bvh_nodes", bvh.get_num_nodes() as u32)?;
+        kernel.set_arg("num_unbounded_objects", bvh.get_num_unbounded_objects())?;
+      }
+      self.uploaded_bvh = Some(bvh_version);
+    }
+
+    let tile_lists = match settings.tile_culling {
+      Some(tile_size) if !settings.is_path_traced() && !settings.progressive => {
+        TileLists::build(camera, scene, self.width, self.height, tile_size)
+      }
+      _ => None
+    };
+    match tile_lists {
+      Some(tile_lists) => {
+        self.upload_data.tile_ranges = tile_lists.get_ranges().to_vec();
+        self.upload_data.tile_objects = tile_lists.get_objects().to_vec();
+        let (ranges, objects) = (&self.upload_data.tile_ranges, &self.upload_data.tile_objects);
+        if ranges.len() > self.tile_range_capacity {
+          self.tile_range_capacity = grow_capacity(self.tile_range_capacity, ranges.len());
+          self.tile_range_buffer = create_index_buffer(&self.pro_que, self.tile_range_capacity)?;
+          self.ray_cast_kernel.set_arg("tile_ranges", &self.tile_range_buffer)?;
+        }
+        if objects.len() > self.tile_object_capacity {
+          self.tile_object_capacity = grow_capacity(self.tile_object_capacity, objects.len());
+          self.tile_object_buffer = create_index_buffer(&self.pro_que, self.tile_object_capacity)?;
+          self.ray_cast_kernel.set_arg("tile_objects", &self.tile_object_buffer)?;
+        }
+        self.upload_event = Some(unsafe {write_without_blocking(&self.tile_range_buffer, ranges, 0)?});
+        if !objects.is_empty() {
+          self.upload_event = Some(unsafe {write_without_blocking(&self.tile_object_buffer, objects, 0)?});
+        }
+        self.ray_cast_kernel.set_arg("tile_size", tile_lists.get_tile_size())?;
+      }
+      None => self.ray_cast_kernel.set_arg("tile_size", 0u32)?
+    }
+
+    self.timings.upload = start.elapsed();
+    Ok(())
+  }
+
+  fn create_aov_passes(&self) -> Result<AovPasses, ocl::Error> {
+    let depth_buffer = self.pro_que.create_buffer::<f32>()?;
+    let normal_buffer = self.pro_que.create_buffer::<Float4>()?;
+    let position_buffer = self.pro_que.create_buffer::<Float4>()?;
+    let object_id_buffer = self.pro_que.create_buffer::<u32>()?;
+    let albedo_buffer = self.pro_que.create_buffer::<Float4>()?;
+    let direct_buffer = self.pro_que.create_buffer::<Float4>()?;
+    let reflection_buffer = self.pro_que.create_buffer::<Float4>()?;
+
+    let kernel = self.pro_que.kernel_builder("rayCastAovs")
+    .arg_named("pixel_buffer", &self.pixel_buffers[0])
+    .arg(&depth_buffer)
+    .arg(&normal_buffer)
+    .arg(&position_buffer)
+    .arg(&object_id_buffer)
+    .arg(&albedo_buffer)
+    .arg(&direct_buffer)
+    .arg(&reflection_buffer)
+    .arg_named("scene_object_integer_buffer", &self.scene_object_integer_buffer)
+    .arg_named("scene_object_float_buffer", &self.scene_object_float_buffer)
+    .arg_named("num_scene_objects", 0u32)
+    .arg_named("bvh_nodes", &self.bvh_node_buffer)
+    .arg_named("bvh_node_ranges", &self.bvh_node_range_buffer)
+    .arg_named("bvh_indices", &self.bvh_index_buffer)
+    .arg_named("num_bvh_nodes", 0u32)
+    .arg_named("num_unbounded_objects", 0u32)
+    .arg_named("quality_dists", Float8::zero())
+    .arg_named("quality_counts", Uint2::zero())
+    .arg_named("camera", Float16::zero())
+    .arg_named("light", Float3::zero())
+    .arg_named("fog", Float8::zero())
+    .arg_named("god_ray_samples", 0u32)
+    .arg_named("tone_mapping", 0u32)
+    .arg_named("exposure", 0f32)
+    .arg(self.width)
+    .arg(self.height)
+    .build()?;
+
+    Ok(AovPasses {depth_buffer: depth_buffer,
+                  normal_buffer: normal_buffer,
+                  position_buffer: position_buffer,
+                  object_id_buffer: object_id_buffer,
+                  albedo_buffer: albedo_buffer,
+                  direct_buffer: direct_buffer,
+                  reflection_buffer: reflection_buffer,
+                  kernel: kernel})
+  }
+
+  fn get_kernels(&self) -> Vec<&Kernel> {
+    let mut kernels = vec![&self.cone_kernel, &self.ray_cast_kernel, &self.adaptive_kernel, &self.progressive_kernel, &self.path_trace_kernel];
+    if let Some(aov_passes) = &self.aov_passes {
+      kernels.push(&aov_passes.kernel);
+    }
+    kernels
+  }
+}
+
+fn create_pixel_buffer(pro_que: &ProQue) -> Result<Buffer<u32>, ocl::Error> {
+  // host accessible memory makes mapping the buffer for reading cheap.
+  pro_que.buffer_builder::<u32>()
+    .flags(MemFlags::new().read_write().alloc_host_ptr())
+    .build()
+}
+
+fn create_scene_buffers(pro_que: &ProQue, capacity: usize) -> Result<(Buffer<Uchar8>, Buffer<Float16>), ocl::Error> {
+  let scene_object_integer_buffer = pro_que.buffer_builder::<Uchar8>()
+    .len(capacity)
+    .flags(MemFlags::READ_ONLY)
+    .build()?;
+  let scene_object_float_buffer = pro_que.buffer_builder::<Float16>()
+    .len(capacity)
+    .flags(MemFlags::READ_ONLY)
+    .build()?;
+  Ok((scene_object_integer_buffer, scene_object_float_buffer))
+}
+
+// the bounds of the nodes, their offsets and counts, and the object indices.
+fn create_bvh_buffers(pro_que: &ProQue, node_capacity: usize, index_capacity: usize) -> Result<(Buffer<Float8>, Buffer<Uint2>, Buffer<u32>), ocl::Error> {
+  let bvh_node_buffer = pro_que.buffer_builder::<Float8>()
+    .len(node_capacity)
+    .flags(MemFlags::READ_ONLY)
+    .build()?;
+  let bvh_node_range_buffer = pro_que.buffer_builder::<Uint2>()
+    .len(node_capacity)
+    .flags(MemFlags::READ_ONLY)
+    .build()?;
+  Ok((bvh_node_buffer, bvh_node_range_buffer, create_index_buffer(pro_que, index_capacity)?))
+}
+
+fn create_index_buffer(pro_que: &ProQue, capacity: usize) -> Result<Buffer<u32>, ocl::Error> {
+  pro_que.buffer_builder::<u32>()
+    .len(capacity)
+    .flags(MemFlags::READ_ONLY)
+    .build()
+}
+
+fn create_cone_buffer(pro_que: &ProQue, capacity: usize) -> Result<Buffer<Float2>, ocl::Error> {
+  pro_que.buffer_builder::<Float2>()
+    .len(capacity)
+    .build()
+}
+
+// enqueues a write that returns without waiting for it, data must not be changed
+// or dropped until the returned event completes.
+unsafe fn write_without_blocking<T: OclPrm>(buffer: &Buffer<T>, data: &[T], offset: usize) -> Result<Event, ocl::Error> {
+  let mut event = Event::empty();
+  buffer.write(data).offset(offset).block(false).enew(&mut event).enq()?;
+  Ok(event)
+}
+
+// reads a pass of float4s, keeping the first three channels of each.
+fn read_float3_pass(buffer: &Buffer<Float4>, len: usize) -> Result<Vec<f32>, ocl::Error> {
+  let mut samples = vec![Float4::zero(); len];
+  buffer.read(&mut samples).enq()?;
+  Ok(samples.iter().flat_map(|sample| sample[..3].iter().cloned()).collect())
+}
+
+// doubles capacity until len fits.
+fn grow_capacity(mut capacity: usize, len: usize) -> usize {
+  while capacity < len {
+    capacity *= 2;
+  }
+  capacity
+}
+
+// sets the arguments every kernel takes from the camera, scene and settings.
+fn set_frame_args(kernel: &Kernel, camera: &Camera, scene: &Scene, settings: &RenderSettings, width: u32, height: u32) -> Result<(), ocl::Error> {
+  kernel.set_arg("num_scene_objects", scene.len() as u32)?;
+  kernel.set_arg("quality_dists", settings.get_quality_data(camera, width, height))?;
+  kernel.set_arg("quality_counts", settings.get_quality_counts())?;
+  kernel.set_arg("camera", camera.get_data())?;
+  kernel.set_arg("light", scene.get_light_data())?;
+  kernel.set_arg("fog", scene.get_fog_data())?;
+  kernel.set_arg("tone_mapping", settings.tone_mapping.get_key())?;
+  kernel.set_arg("exposure", settings.exposure)?;
+  Ok(())
+}
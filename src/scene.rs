@@ -1,52 +1,169 @@
 extern crate ocl;
-use ocl::Buffer;
-use ocl::ProQue;
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use crate::scene_objects::scene_object::SceneObject;
-use ocl::prm::{Uchar8, Float16};
-use ocl::flags::MemFlags;
+use crate::fog::Fog;
+use crate::math::{Vec3, Aabb};
+use crate::bvh::Bvh;
+use crate::render_settings::Quality;
+use ocl::prm::{Uchar8, Float3, Float8, Float16};
 
+// the id of the next scene created.
+static NEXT_SCENE_ID: AtomicU64 = AtomicU64::new(0);
 
 pub struct Scene {
-  scene_objects: Vec<Box<dyn SceneObject>>
+  id: u64,
+  scene_objects: Vec<Box<dyn SceneObject>>,
+  fog: Fog,
+  light_position: Vec3,
+  version: u64,
+  // the version each object was last changed in.
+  object_versions: Vec<u64>,
+  // the version objects were last added, changed or removed in.
+  objects_version: u64
 }
 
 impl Scene {
   pub fn new() -> Self {
-    Scene {scene_objects: Vec::new()}
+    Scene {id: NEXT_SCENE_ID.fetch_add(1, Ordering::Relaxed), scene_objects: Vec::new(), fog: Fog::none(), light_position: Vec3::new(0., 20., 5.), version: 0, object_versions: Vec::new(), objects_version: 0}
   }
 
   pub fn push(&mut self, obj: Box<dyn SceneObject>) {
     self.scene_objects.push(obj);
+    self.version += 1;
+    self.objects_version = self.version;
+    self.object_versions.push(self.version);
   }
 
-  fn to_ocl_format(&self) -> (Vec<Uchar8>, Vec<Float16>) {
-    let mut objects_integer_data = Vec::<Uchar8>::with_capacity(self.scene_objects.len());
-    let mut objects_float_data = Vec::<Float16>::with_capacity(self.scene_objects.len());
-    for object in &self.scene_objects {
-      objects_integer_data.push(object.get_integer_data());
-      objects_float_data.push(object.get_float_data());
+  /// swaps the object at index for another one.
+  pub fn replace(&mut self, index: usize, obj: Box<dyn SceneObject>) {
+    self.scene_objects[index] = obj;
+    self.version += 1;
+    self.objects_version = self.version;
+    self.object_versions[index] = self.version;
+  }
+
+  /// removes the object at index, every object after it moves down one index.
+  pub fn remove(&mut self, index: usize) -> Box<dyn SceneObject> {
+    let obj = self.scene_objects.remove(index);
+    self.object_versions.remove(index);
+    self.version += 1;
+    self.objects_version = self.version;
+    for object_version in &mut self.object_versions[index..] {
+      *object_version = self.version;
     }
-    (objects_integer_data, objects_float_data)
+    obj
+  }
+
+  pub fn len(&self) -> usize {
+    self.scene_objects.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.scene_objects.is_empty()
+  }
+
+  pub fn set_fog(&mut self, fog: Fog) {
+    self.fog = fog;
+    self.version += 1;
+  }
+
+  /// position of the point light.
+  pub fn set_light_position(&mut self, light_position: Vec3) {
+    self.light_position = light_position;
+    self.version += 1;
+  }
+
+  /// unique to this scene, versions of different scenes can't be compared.
+  pub fn get_id(&self) -> u64 {
+    self.id
+  }
+
+  /// incremented on every change to the scene.
+  pub fn get_version(&self) -> u64 {
+    self.version
+  }
+
+  /// the version objects were last added, changed or removed in, fog and light
+  /// changes don't count.
+  pub fn get_objects_version(&self) -> u64 {
+    self.objects_version
   }
 
-  pub fn to_ocl_buffer(&self, pro_que: &ProQue) -> Result<(u32, Buffer<Uchar8>, Buffer<Float16>), ocl::Error>{
-    let (objects_integer_data, objects_float_data) = self.to_ocl_format();
+  /// bounds of each object, none for the unbounded ones.
+  pub fn get_object_bounds(&self) -> Vec<Option<Aabb>> {
+    self.scene_objects.iter().map(|object| object.get_bounds()).collect()
+  }
+
+  /// bounding volume hierarchy over the objects as they are now.
+  pub fn build_bvh(&self) -> Bvh {
+    Bvh::build(&self.get_object_bounds())
+  }
+
+  pub fn get_fog_data(&self) -> Float8 {
+    self.fog.get_data()
+  }
+
+  pub fn get_light_data(&self) -> Float3 {
+    Float3::from(self.light_position)
+  }
+
+  /// distance to the closest object and its index.
+  pub fn distance(&self, point: Vec3) -> (f32, usize) {
+    let mut min_dist = std::f32::MAX;
+    let mut min_obj = 0;
+    for (i, object) in self.scene_objects.iter().enumerate() {
+      let dist = object.distance(point);
+      if dist < min_dist {
+        min_dist = dist;
+        min_obj = i;
+      }
+    }
+    (min_dist, min_obj)
+  }
 
-    let scene_object_integer_buffer = pro_que.buffer_builder::<Uchar8>()
-      .len(self.scene_objects.len())
-      .flags(MemFlags::READ_ONLY)
-      .build()?;
-    let scene_object_float_buffer = pro_que.buffer_builder::<Float16>()
-      .len(self.scene_objects.len())
-      .flags(MemFlags::READ_ONLY)
-      .build()?;
+  /// sphere traces the ray on the cpu with the marching limits of quality, returns
+  /// the hit point and the index of the object hit. direction must be normalized.
+  pub fn raycast(&self, start: Vec3, direction: Vec3, quality: &Quality) -> Option<(Vec3, usize)> {
+    let mut point = start;
+    for _ in 0..quality.max_iterations {
+      let (dist, obj_index) = self.distance(point);
+      if dist < quality.smallest_dist {
+        return Some((point, obj_index));
+      }
+      if dist >= quality.max_dist {
+        return None;
+      }
+      point += direction*dist;
+    }
+    None
+  }
 
-    scene_object_integer_buffer.write(objects_integer_data.as_slice()).enq()?;
-    scene_object_float_buffer.write(objects_float_data.as_slice()).enq()?;
+  /// smallest range of object indices holding every object changed after version,
+  /// none when no object has changed.
+  pub fn get_changed_range(&self, version: u64) -> Option<Range<usize>> {
+    let first = self.object_versions.iter().position(|&object_version| object_version > version)?;
+    let last = self.object_versions.iter().rposition(|&object_version| object_version > version)?;
+    Some(first..last + 1)
+  }
+
+  /// the objects in range packed for the kernel.
+  pub fn get_ocl_data(&self, range: Range<usize>) -> (Vec<Uchar8>, Vec<Float16>) {
+    let objects = &self.scene_objects[range];
+    let mut objects_integer_data = Vec::<Uchar8>::with_capacity(objects.len());
+    let mut objects_float_data = Vec::<Float16>::with_capacity(objects.len());
+    for object in objects {
+      objects_integer_data.push(object.get_integer_data());
+      objects_float_data.push(object.get_float_data());
+    }
+    (objects_integer_data, objects_float_data)
+  }
+}
 
-    Ok((self.scene_objects.len() as u32, 
-        scene_object_integer_buffer, 
-        scene_object_float_buffer))
+impl Default for Scene {
+  fn default() -> Self {
+    Scene::new()
   }
 }
\ No newline at end of file
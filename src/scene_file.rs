@@ -0,0 +1,227 @@
+use std::fs;
+use std::path::Path;
+
+use crate::camera::Camera;
+use crate::error::Error;
+use crate::fog::Fog;
+use crate::math::Vec3;
+use crate::projection::Projection;
+use crate::render_settings::Quality;
+use crate::scene::Scene;
+use crate::scene_objects::material::Material;
+use crate::scene_objects::sphere::Sphere;
+use crate::scene_objects::floor_plane::FloorPlane;
+use crate::scene_objects::capsule::Capsule;
+use crate::scene_objects::cylinder::Cylinder;
+use crate::scene_objects::boxx::Boxx;
+
+const DEFAULT_FOV: f32 = 60.;
+
+/// a scene and the camera to look at it from, read from a text file.
+///
+/// one item per line and # starts a comment. positions are x y z, colors are
+/// 0 to 255 r g b and angles are radians. objects can end with `roughness <r>`
+/// and `emission <e>`. the camera is a pinhole unless given an aperture radius,
+/// it focuses at the focus distance, or with autofocus on whatever it looks at
+/// once every object is read.
+///
+/// ```text
+/// camera <position> look_at <position> [fov <degrees>] [aperture <radius>] [focus <distance> | autofocus]
+/// light <position>
+/// fog <color> <density> <height> <height_density> <height_falloff>
+/// sphere <position> <radius> <color> <reflectivity>
+/// floor <height> <color> <reflectivity>
+/// capsule <position> <position> <radius> <color> <reflectivity>
+/// cylinder <position> <position> <radius> <color> <reflectivity>
+/// box <position> <scale> <rotation> <color> <reflectivity>
+/// ```
+pub struct SceneFile {
+  pub scene: Scene,
+  pub camera: Camera
+}
+
+impl SceneFile {
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+    SceneFile::parse(&fs::read_to_string(path)?)
+  }
+
+  pub fn parse(source: &str) -> Result<Self, Error> {
+    let mut scene = Scene::new();
+    let mut camera = Camera::new(Vec3::new(0., 10., -10.), Vec3::ZERO, Projection::perspective(DEFAULT_FOV));
+    // the camera line asking for autofocus, done once every object is read.
+    let mut autofocus_line = None;
+
+    for (line_number, line) in source.lines().enumerate() {
+      let line = line.split('#').next().unwrap_or("");
+      let mut tokens = Tokens {tokens: line.split_whitespace().collect(), next: 0, line_number: line_number + 1};
+      let item = match tokens.next() {
+        Some(item) => item,
+        None => continue
+      };
+
+      match item {
+        "camera" => {
+          let position = tokens.vec3()?;
+          tokens.keyword("look_at")?;
+          let target = tokens.vec3()?;
+          camera.set_position(position);
+          camera.look_at(target);
+          let mut fov = DEFAULT_FOV;
+          while let Some(token) = tokens.next() {
+            match token {
+              "fov" => fov = tokens.float()?,
+              "aperture" => camera.set_aperture(tokens.float()?),
+              "focus" => camera.set_focus_distance(tokens.float()?),
+              "autofocus" => autofocus_line = Some(line_number + 1),
+              other => return Err(tokens.error(&format!("unknown property {}", other)))
+            }
+          }
+          camera.set_projection(Projection::perspective(fov));
+        }
+        "light" => scene.set_light_position(tokens.vec3()?),
+        "fog" => {
+          let color = tokens.color()?;
+          scene.set_fog(Fog::new(color, tokens.float()?, tokens.float()?, tokens.float()?, tokens.float()?));
+        }
+        "sphere" => scene.push(Box::new(Sphere::new(tokens.vec3()?, tokens.float()?, tokens.color()?, tokens.material()?))),
+        "floor" => scene.push(Box::new(FloorPlane::new(tokens.float()?, tokens.color()?, tokens.material()?))),
+        "capsule" => scene.push(Box::new(Capsule::new(tokens.vec3()?, tokens.vec3()?, tokens.float()?, tokens.color()?, tokens.material()?))),
+        "cylinder" => scene.push(Box::new(Cylinder::new(tokens.vec3()?, tokens.vec3()?, tokens.float()?, tokens.color()?, tokens.material()?))),
+        "box" => scene.push(Box::new(Boxx::new(tokens.vec3()?, tokens.vec3()?, tokens.vec3()?, tokens.color()?, tokens.material()?))),
+        other => return Err(tokens.error(&format!("unknown item {}", other)))
+      }
+
+      if !tokens.is_empty() {
+        return Err(tokens.error("too many values"));
+      }
+    }
+
+    if let Some(line_number) = autofocus_line {
+      if !camera.autofocus(&scene, &Quality::FINAL) {
+        return Err(Error::Parse(format!("line {}: autofocus hits nothing", line_number)));
+      }
+    }
+
+    Ok(SceneFile {scene: scene, camera: camera})
+  }
+}
+
+struct Tokens<'a> {
+  tokens: Vec<&'a str>,
+  next: usize,
+  line_number: usize
+}
+
+impl<'a> Tokens<'a> {
+  fn next(&mut self) -> Option<&'a str> {
+    let token = self.tokens.get(self.next).cloned();
+    self.next += 1;
+    token
+  }
+  fn is_empty(&self) -> bool {
+    self.next >= self.tokens.len()
+  }
+  fn error(&self, message: &str) -> Error {
+    Error::Parse(format!("line {}: {}", self.line_number, message))
+  }
+  fn float(&mut self) -> Result<f32, Error> {
+    let token = self.next().ok_or_else(|| self.error("too few values"))?;
+    token.parse::<f32>().map_err(|_| self.error(&format!("{} is not a number", token)))
+  }
+  fn vec3(&mut self) -> Result<Vec3, Error> {
+    Ok(Vec3::new(self.float()?, self.float()?, self.float()?))
+  }
+  fn color(&mut self) -> Result<(u8, u8, u8), Error> {
+    let mut channel = || -> Result<u8, Error> {
+      let token = self.next().ok_or_else(|| self.error("too few values"))?;
+      token.parse::<u8>().map_err(|_| self.error(&format!("{} is not a color channel from 0 to 255", token)))
+    };
+    Ok((channel()?, channel()?, channel()?))
+  }
+  fn keyword(&mut self, keyword: &str) -> Result<(), Error> {
+    match self.next() {
+      Some(token) if token == keyword => Ok(()),
+      _ => Err(self.error(&format!("expected {}", keyword)))
+    }
+  }
+  /// the reflectivity and the optional roughness and emission at the end of an object.
+  fn material(&mut self) -> Result<Material, Error> {
+    let mut material = Material::new(self.float()?);
+    while let Some(token) = self.next() {
+      material = match token {
+        "roughness" => material.with_roughness(self.float()?),
+        "emission" => material.with_emission(self.float()?),
+        other => return Err(self.error(&format!("unknown property {}", other)))
+      };
+    }
+    Ok(material)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const EPSILON: f32 = 1e-5;
+
+  fn parse(source: &str) -> SceneFile {
+    match SceneFile::parse(source) {
+      Ok(file) => file,
+      Err(error) => panic!("{}", error)
+    }
+  }
+
+  fn parse_error(source: &str) -> String {
+    match SceneFile::parse(source) {
+      Ok(_) => panic!("parsed {}", source),
+      Err(error) => error.to_string()
+    }
+  }
+
+  #[test]
+  fn default_scene() {
+    let file = parse(include_str!("../scenes/default.scene"));
+    assert_eq!(file.scene.len(), 8);
+    assert!((file.camera.get_position() - Vec3::new(0., 10., -10.)).length() < EPSILON);
+    assert!((file.camera.get_look_dir() - Vec3::new(0., 0., 1.)).length() < EPSILON);
+    // inside the small white sphere, one unit from its surface.
+    let (dist, index) = file.scene.distance(Vec3::new(0., 1., 0.));
+    assert!((dist + 1.).abs() < EPSILON);
+    assert_eq!(index, 2);
+    // the materials after the reflectivity.
+    let (_, data) = file.scene.get_ocl_data(0..file.scene.len());
+    assert!((data[3][14] - 20.).abs() < EPSILON);
+    assert!((data[7][13] - 0.2).abs() < EPSILON);
+    assert!((data[7][15] - 0.3).abs() < EPSILON);
+  }
+
+  #[test]
+  fn depth_of_field() {
+    let file = parse("camera 0 1 -5 look_at 0 1 0 aperture 0.1 focus 7");
+    assert!((file.camera.get_aperture() - 0.1).abs() < EPSILON);
+    assert!((file.camera.get_focus_distance() - 7.).abs() < EPSILON);
+    // the sphere is read after the camera and its near side is 4 away.
+    let file = parse("camera 0 1 -5 look_at 0 1 0 fov 40 aperture 0.1 autofocus\nsphere 0 1 0 1 255 255 255 0");
+    assert!((file.camera.get_focus_distance() - 4.).abs() < 0.01);
+  }
+
+  #[test]
+  fn comments_and_blank_lines() {
+    let file = parse("# nothing here\n\n   \nsphere 0 0 0 1 255 255 255 0 # a sphere\n");
+    assert_eq!(file.scene.len(), 1);
+  }
+
+  #[test]
+  fn errors() {
+    assert!(parse_error("\nteapot 0 0 0").ends_with("line 2: unknown item teapot"));
+    assert!(parse_error("sphere 0 0 0 1 255 255").ends_with("line 1: too few values"));
+    assert!(parse_error("light 0 20 5 1").ends_with("line 1: too many values"));
+    assert!(parse_error("camera 0 0 0 look_at 0 0 1 fov 60 zoom 2").ends_with("line 1: unknown property zoom"));
+    assert!(parse_error("camera 0 0 0 look_at 0 0 1 autofocus\nfloor -1 0 0 0 0").ends_with("line 1: autofocus hits nothing"));
+    assert!(parse_error("fog 0 0 0 0.1 0 0 0 0").ends_with("line 1: too many values"));
+    assert!(parse_error("floor x 0 0 0 0").ends_with("line 1: x is not a number"));
+    assert!(parse_error("floor 0 0 256 0 0").ends_with("line 1: 256 is not a color channel from 0 to 255"));
+    assert!(parse_error("camera 0 0 0 0 0 1").ends_with("line 1: expected look_at"));
+    assert!(parse_error("floor 0 0 0 0 0 shininess 1").ends_with("line 1: unknown property shininess"));
+  }
+}
@@ -1,32 +1,57 @@
 extern crate ocl;
 
 use super::scene_object::SceneObject;
+use super::material::Material;
 use ocl::prm::{Uchar8, Float16};
 
+use crate::math::{Vec3, Transform, Quat, Aabb};
+
 const BOX_KEY: u8 = 4;
 
 pub struct Boxx {
-  position: (f32, f32, f32),
-  scale: (f32, f32, f32),
-  rotation: (f32, f32, f32),
+  position: Vec3,
+  scale: Vec3,
+  rotation: Vec3,
   color: (u8, u8, u8),
-  reflectivity: f32
+  material: Material
 }
 
 impl Boxx {
-  pub fn new(position: (f32, f32, f32), scale: (f32, f32, f32), rotation: (f32, f32, f32), color: (u8, u8, u8), reflectivity: f32) -> Self {
-    Boxx {position: position, scale: scale, rotation: rotation, color:color, reflectivity: reflectivity}
+  pub fn new(position: Vec3, scale: Vec3, rotation: Vec3, color: (u8, u8, u8), material: Material) -> Self {
+    Boxx {position: position, scale: scale, rotation: rotation, color:color, material: material}
+  }
+  // takes world points to the space the box is centered and axis aligned in.
+  // rotates around the middle of position and position + scale, the same as the kernel.
+  fn to_box(&self) -> Transform {
+    let center = self.position + self.scale/2.;
+    Transform::from_translation(center - self.position)
+      *Transform::from_rotation(Quat::from_euler(self.rotation))
+      *Transform::from_translation(-center)
   }
 }
 
 impl SceneObject for Boxx {
   fn get_float_data(&self) -> Float16 {
-    Float16::new(self.position.0,self.position.1,self.position.2,
-      self.scale.0,self.scale.1,self.scale.2,
-      self.rotation.0,self.rotation.1,self.rotation.2,
-      0.,0.,0.,0.,0.,0.,self.reflectivity)
+    self.material.pack(Float16::new(self.position.x,self.position.y,self.position.z,
+      self.scale.x,self.scale.y,self.scale.z,
+      self.rotation.x,self.rotation.y,self.rotation.z,
+      0.,0.,0.,0.,0.,0.,0.))
   }
   fn get_integer_data(&self) -> Uchar8 {
     Uchar8::new(BOX_KEY, self.color.0, self.color.1, self.color.2, 0, 0, 0, 0)
   }
+  fn distance(&self, point: Vec3) -> f32 {
+    let tpos = self.to_box().transform_point(point);
+
+    let q = tpos.abs() - self.scale;
+    q.max(Vec3::ZERO).length() + q.max_element().min(0.)
+  }
+  fn get_bounds(&self) -> Option<Aabb> {
+    // the rotated half extents projected onto each axis.
+    let from_box = self.to_box().inverse();
+    let half_extents = from_box.transform_vector(Vec3::X*self.scale.x).abs()
+      + from_box.transform_vector(Vec3::Y*self.scale.y).abs()
+      + from_box.transform_vector(Vec3::Z*self.scale.z).abs();
+    Some(Aabb::from_center_half_extents(from_box.transform_point(Vec3::ZERO), half_extents))
+  }
 }
\ No newline at end of file
@@ -1,29 +1,43 @@
 extern crate ocl;
 
 use super::scene_object::SceneObject;
+use super::material::Material;
 use ocl::prm::{Uchar8, Float16};
 
+use crate::math::{Vec3, Aabb};
+
 const CAPSULE_KEY: u8 = 2;
 
 pub struct Capsule {
-  position1: (f32, f32, f32),
-  position2: (f32, f32, f32),
+  position1: Vec3,
+  position2: Vec3,
   radius: f32,
   color: (u8, u8, u8),
-  reflectivity: f32
+  material: Material
 }
 
 impl Capsule {
-  pub fn new(position1: (f32, f32, f32), position2: (f32, f32, f32), radius: f32, color: (u8, u8, u8), reflectivity: f32) -> Self {
-    Capsule {position1: position1, position2: position2, radius: radius, color:color, reflectivity: reflectivity}
+  pub fn new(position1: Vec3, position2: Vec3, radius: f32, color: (u8, u8, u8), material: Material) -> Self {
+    Capsule {position1: position1, position2: position2, radius: radius, color:color, material: material}
   }
 }
 
 impl SceneObject for Capsule {
   fn get_float_data(&self) -> Float16 {
-    Float16::new(self.position1.0,self.position1.1,self.position1.2,self.position2.0,self.position2.1,self.position2.2,self.radius,0.,0.,0.,0.,0.,0.,0.,0.,self.reflectivity)
+    self.material.pack(Float16::new(self.position1.x,self.position1.y,self.position1.z,self.position2.x,self.position2.y,self.position2.z,self.radius,0.,0.,0.,0.,0.,0.,0.,0.,0.))
   }
   fn get_integer_data(&self) -> Uchar8 {
     Uchar8::new(CAPSULE_KEY, self.color.0, self.color.1, self.color.2, 0, 0, 0, 0)
   }
+  fn distance(&self, point: Vec3) -> f32 {
+    let ab = self.position2 - self.position1;
+    let ap = point - self.position1;
+    let t = (ab.dot(ap)/ab.dot(ab)).max(0.).min(1.);
+    let proj = self.position1 + ab*t;
+    (point - proj).length() - self.radius
+  }
+  fn get_bounds(&self) -> Option<Aabb> {
+    let radius = Vec3::splat(self.radius);
+    Some(Aabb::new(self.position1.min(self.position2) - radius, self.position1.max(self.position2) + radius))
+  }
 }
\ No newline at end of file
@@ -1,27 +1,36 @@
 extern crate ocl;
 
 use super::scene_object::SceneObject;
+use super::material::Material;
 use ocl::prm::{Uchar8, Float16};
 
+use crate::math::{Vec3, Aabb};
+
 const FLOORPLANE_KEY: u8 = 1;
 
 pub struct FloorPlane {
   height: f32,
   color: (u8, u8, u8),
-  reflectivity: f32
+  material: Material
 }
 
 impl FloorPlane {
-  pub fn new(height: f32, color: (u8, u8, u8), reflectivity: f32) -> Self {
-    FloorPlane {height: height, color:color, reflectivity: reflectivity}
+  pub fn new(height: f32, color: (u8, u8, u8), material: Material) -> Self {
+    FloorPlane {height: height, color:color, material: material}
   }
 }
 
 impl SceneObject for FloorPlane {
   fn get_float_data(&self) -> Float16 {
-    Float16::new(self.height,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,self.reflectivity)
+    self.material.pack(Float16::new(self.height,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.))
   }
   fn get_integer_data(&self) -> Uchar8 {
     Uchar8::new(FLOORPLANE_KEY, self.color.0, self.color.1, self.color.2, 0, 0, 0, 0)
   }
+  fn distance(&self, point: Vec3) -> f32 {
+    point.y - self.height
+  }
+  fn get_bounds(&self) -> Option<Aabb> {
+    None
+  }
 }
\ No newline at end of file
@@ -0,0 +1,36 @@
+extern crate ocl;
+
+use ocl::prm::Float16;
+
+/// how the surface of an object reflects and emits light.
+#[derive(Clone, Copy)]
+pub struct Material {
+  reflectivity: f32,
+  roughness: f32,
+  emission: f32
+}
+
+impl Material {
+  /// reflectivity is the fraction of the light mirrored by the whitted integrator.
+  pub fn new(reflectivity: f32) -> Self {
+    Material {reflectivity: reflectivity, roughness: 0., emission: 0.}
+  }
+  /// spread of the specular lobe used by the path tracer, 0 is a perfect mirror.
+  pub fn with_roughness(mut self, roughness: f32) -> Self {
+    self.roughness = roughness;
+    self
+  }
+  /// radiance emitted in the color of the object, makes it a light for the path tracer.
+  pub fn with_emission(mut self, emission: f32) -> Self {
+    self.emission = emission;
+    self
+  }
+  /// the float data of an object with the material in its last three lanes,
+  /// where the kernel reads it for every object.
+  pub fn pack(&self, mut data: Float16) -> Float16 {
+    data[13] = self.roughness;
+    data[14] = self.emission;
+    data[15] = self.reflectivity;
+    data
+  }
+}
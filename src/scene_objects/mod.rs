@@ -1,4 +1,5 @@
 pub mod scene_object;
+pub mod material;
 pub mod sphere;
 pub mod capsule;
 pub mod cylinder;
@@ -2,7 +2,13 @@ extern crate ocl;
 
 use ocl::prm::{Uchar8, Float16};
 
+use crate::math::{Vec3, Aabb};
+
 pub trait SceneObject{
   fn get_integer_data(&self) -> Uchar8;
   fn get_float_data(&self) -> Float16;
+  /// signed distance from point to the surface, the same as the kernel's.
+  fn distance(&self, point: Vec3) -> f32;
+  /// box holding the whole object, none when it is unbounded.
+  fn get_bounds(&self) -> Option<Aabb>;
 }
\ No newline at end of file
@@ -1,28 +1,37 @@
 extern crate ocl;
 
 use super::scene_object::SceneObject;
+use super::material::Material;
 use ocl::prm::{Uchar8, Float16};
 
+use crate::math::{Vec3, Aabb};
+
 const SPHERE_KEY: u8 = 0;
 
 pub struct Sphere {
-  position: (f32, f32, f32),
+  position: Vec3,
   radius: f32,
   color: (u8, u8, u8),
-  reflectivity: f32
+  material: Material
 }
 
 impl Sphere {
-  pub fn new(position: (f32, f32, f32), radius: f32, color: (u8, u8, u8), reflectivity: f32) -> Self {
-    Sphere {position: position, radius: radius, color:color, reflectivity: reflectivity}
+  pub fn new(position: Vec3, radius: f32, color: (u8, u8, u8), material: Material) -> Self {
+    Sphere {position: position, radius: radius, color:color, material: material}
   }
 }
 
 impl SceneObject for Sphere {
   fn get_float_data(&self) -> Float16 {
-    Float16::new(self.position.0,self.position.1,self.position.2,self.radius,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,self.reflectivity)
+    self.material.pack(Float16::new(self.position.x,self.position.y,self.position.z,self.radius,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.,0.))
   }
   fn get_integer_data(&self) -> Uchar8 {
     Uchar8::new(SPHERE_KEY, self.color.0, self.color.1, self.color.2, 0, 0, 0, 0)
   }
+  fn distance(&self, point: Vec3) -> f32 {
+    (point - self.position).length() - self.radius
+  }
+  fn get_bounds(&self) -> Option<Aabb> {
+    Some(Aabb::from_center_half_extents(self.position, Vec3::splat(self.radius)))
+  }
 }
\ No newline at end of file
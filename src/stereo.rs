@@ -0,0 +1,148 @@
+use std::str::FromStr;
+
+use crate::camera::Camera;
+use crate::error::Error;
+
+/// the eye distances used when only a layout is given.
+pub const DEFAULT_INTEROCULAR_DISTANCE: f32 = 0.5;
+pub const DEFAULT_CONVERGENCE_DISTANCE: f32 = 15.;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum StereoLayout {
+  /// each eye squeezed to half the width, left eye on the left.
+  SideBySide,
+  /// each eye squeezed to half the height, left eye on top.
+  TopBottom,
+  /// red channel from the left eye, green and blue from the right, for red/cyan glasses.
+  Anaglyph
+}
+
+impl FromStr for StereoLayout {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self, Error> {
+    match s {
+      "side-by-side" => Ok(StereoLayout::SideBySide),
+      "top-bottom" => Ok(StereoLayout::TopBottom),
+      "anaglyph" => Ok(StereoLayout::Anaglyph),
+      _ => Err(Error::Parse(format!("unknown stereo layout {}", s)))
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Stereo {
+  pub layout: StereoLayout,
+  /// distance between the two eye positions.
+  pub interocular_distance: f32,
+  /// distance along the look direction at which the eyes have zero parallax.
+  pub convergence_distance: f32
+}
+
+impl Stereo {
+  pub fn new(layout: StereoLayout, interocular_distance: f32, convergence_distance: f32) -> Self {
+    Stereo {layout: layout, interocular_distance: interocular_distance, convergence_distance: convergence_distance}
+  }
+
+  /// off axis eye cameras, they keep the look direction of the camera and
+  /// shift their frustums to converge instead of toeing in.
+  pub fn get_eyes(&self, camera: &Camera) -> (Camera, Camera) {
+    let half_distance = self.interocular_distance/2.;
+    let shift = half_distance/self.convergence_distance;
+    (camera.eye(-half_distance, shift), camera.eye(half_distance, -shift))
+  }
+
+  /// combines the packed 0x00RRGGBB images of the two eyes into one image of the same size.
+  pub fn composite(&self, left: &[u32], right: &[u32], width: usize, height: usize) -> Vec<u32> {
+    let mut out = vec![0; width*height];
+    for y in 0..height {
+      for x in 0..width {
+        out[y*width + x] = match self.layout {
+          StereoLayout::SideBySide => {
+            let half_width = width/2;
+            let (eye, eye_x) = if x < half_width {(left, x)} else {(right, x - half_width)};
+            // the pair of pixels squeezed into one, a single pixel in images one wide.
+            let src_x = (2*eye_x).min(width.saturating_sub(2));
+            average(eye[y*width + src_x], eye[y*width + (src_x + 1).min(width - 1)])
+          }
+          StereoLayout::TopBottom => {
+            let half_height = height/2;
+            let (eye, eye_y) = if y < half_height {(left, y)} else {(right, y - half_height)};
+            let src_y = (2*eye_y).min(height.saturating_sub(2));
+            average(eye[src_y*width + x], eye[(src_y + 1).min(height - 1)*width + x])
+          }
+          StereoLayout::Anaglyph => {
+            let i = y*width + x;
+            (left[i] & 0xFF0000) | (right[i] & 0x00FFFF)
+          }
+        };
+      }
+    }
+    out
+  }
+}
+
+/// a layout, optionally followed by :<interocular distance>:<convergence distance>.
+impl FromStr for Stereo {
+  type Err = Error;
+  fn from_str(s: &str) -> Result<Self, Error> {
+    let parts: Vec<&str> = s.split(':').collect();
+    let invalid = || Error::Parse(format!("invalid stereo {}", s));
+    let distance = |i: usize| parts[i].parse::<f32>().ok().filter(|&d| d > 0.).ok_or_else(invalid);
+    let layout = parts[0].parse()?;
+    match parts.len() {
+      1 => Ok(Stereo::new(layout, DEFAULT_INTEROCULAR_DISTANCE, DEFAULT_CONVERGENCE_DISTANCE)),
+      3 => Ok(Stereo::new(layout, distance(1)?, distance(2)?)),
+      _ => Err(invalid())
+    }
+  }
+}
+
+fn average(a: u32, b: u32) -> u32 {
+  let mut out = 0;
+  for shift in &[0, 8, 16] {
+    let channel = (((a >> shift) & 0xFF) + ((b >> shift) & 0xFF))/2;
+    out |= channel << shift;
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn composite(layout: StereoLayout, left: &[u32], right: &[u32], width: usize, height: usize) -> Vec<u32> {
+    Stereo::new(layout, 1., 1.).composite(left, right, width, height)
+  }
+
+  #[test]
+  fn side_by_side() {
+    // each channel is averaged on its own.
+    assert_eq!(composite(StereoLayout::SideBySide, &[0x0000FF, 0xFF0000], &[0x00FF00, 0x00FF00], 2, 1), vec![0x7F007F, 0x00FF00]);
+    // the last pixel of an odd width only has the pixel before it to pair with.
+    assert_eq!(composite(StereoLayout::SideBySide, &[0, 2, 4, 6, 8], &[10, 12, 14, 16, 18], 5, 1), vec![1, 5, 11, 15, 17]);
+    assert_eq!(composite(StereoLayout::SideBySide, &[0, 2], &[10, 12], 1, 2), vec![10, 12]);
+  }
+
+  #[test]
+  fn top_bottom() {
+    assert_eq!(composite(StereoLayout::TopBottom, &[0, 2, 4, 6], &[10, 12, 14, 16], 1, 4), vec![1, 5, 11, 15]);
+    assert_eq!(composite(StereoLayout::TopBottom, &[0, 2, 4], &[10, 12, 14], 1, 3), vec![1, 11, 13]);
+    assert_eq!(composite(StereoLayout::TopBottom, &[0, 2], &[10, 12], 2, 1), vec![10, 12]);
+  }
+
+  #[test]
+  fn anaglyph() {
+    assert_eq!(composite(StereoLayout::Anaglyph, &[0x123456, 0xFFFFFF], &[0xABCDEF, 0], 2, 1), vec![0x12CDEF, 0xFF0000]);
+  }
+
+  #[test]
+  fn from_str() {
+    let stereo: Stereo = "top-bottom".parse().ok().unwrap();
+    assert!(stereo == Stereo::new(StereoLayout::TopBottom, DEFAULT_INTEROCULAR_DISTANCE, DEFAULT_CONVERGENCE_DISTANCE));
+    let stereo: Stereo = "anaglyph:0.25:10".parse().ok().unwrap();
+    assert!(stereo == Stereo::new(StereoLayout::Anaglyph, 0.25, 10.));
+    assert!("anaglyph:0.25".parse::<Stereo>().is_err());
+    assert!("anaglyph:0:10".parse::<Stereo>().is_err());
+    assert!("over-under".parse::<Stereo>().is_err());
+  }
+}
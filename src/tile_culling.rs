@@ -0,0 +1,98 @@
+use crate::camera::Camera;
+use crate::scene::Scene;
+use crate::math::Vec3;
+
+/// the objects the primary rays of each square tile of the screen can hit, found
+/// by testing the bounding sphere of every object against the frustum of the tile.
+/// secondary rays are not culled, they need every object.
+pub struct TileLists {
+  tile_size: u32,
+  // offset into objects and count of each tile, tiles row by row from the top.
+  ranges: Vec<u32>,
+  objects: Vec<u32>
+}
+
+// a plane through the frustum apex, positive on the inside.
+struct Plane {
+  normal: Vec3,
+  offset: f32
+}
+
+impl Plane {
+  fn through_apex(apex: Vec3, a: Vec3, b: Vec3, inside: Vec3) -> Self {
+    let normal = a.cross(b).normalize();
+    let normal = if normal.dot(inside) < 0. {-normal} else {normal};
+    Plane {normal: normal, offset: normal.dot(apex)}
+  }
+  fn distance(&self, point: Vec3) -> f32 {
+    self.normal.dot(point) - self.offset
+  }
+}
+
+impl TileLists {
+  /// none when the camera's primary rays don't fit in a frustum per tile,
+  /// see Camera::get_frustum_apex.
+  pub fn build(camera: &Camera, scene: &Scene, width: u32, height: u32, tile_size: u32) -> Option<Self> {
+    let apex = camera.get_frustum_apex()?;
+    let tile_size = tile_size.max(1);
+
+    let mut unbounded = Vec::new();
+    let mut spheres = Vec::new();
+    for (i, bounds) in scene.get_object_bounds().into_iter().enumerate() {
+      match bounds {
+        Some(bounds) => spheres.push((i as u32, bounds.center(), bounds.size().length()/2.)),
+        None => unbounded.push(i as u32)
+      }
+    }
+
+    let tiles_x = (width + tile_size - 1)/tile_size;
+    let tiles_y = (height + tile_size - 1)/tile_size;
+    let mut ranges = Vec::with_capacity((2*tiles_x*tiles_y) as usize);
+    let mut objects = Vec::new();
+    for tile_y in 0..tiles_y {
+      for tile_x in 0..tiles_x {
+        // a pixel of margin covers the anti-aliasing offsets and the half pixel
+        // the projections add.
+        let left = (tile_x*tile_size) as f32 - 1.;
+        let top = (tile_y*tile_size) as f32 - 1.;
+        let right = ((tile_x + 1)*tile_size).min(width) as f32 + 1.;
+        let bottom = ((tile_y + 1)*tile_size).min(height) as f32 + 1.;
+
+        let ray = |x: f32, y: f32| camera.get_ray((x, y), width, height).map(|(_, direction)| direction);
+        let planes = match (ray(left, top), ray(right, top), ray(right, bottom), ray(left, bottom), ray((left + right)/2., (top + bottom)/2.)) {
+          (Some(top_left), Some(top_right), Some(bottom_right), Some(bottom_left), Some(center)) => [
+            Plane::through_apex(apex, top_left, top_right, center),
+            Plane::through_apex(apex, top_right, bottom_right, center),
+            Plane::through_apex(apex, bottom_right, bottom_left, center),
+            Plane::through_apex(apex, bottom_left, top_left, center)
+          ],
+          _ => return None
+        };
+
+        let offset = objects.len() as u32;
+        objects.extend_from_slice(&unbounded);
+        for &(index, center, radius) in &spheres {
+          if planes.iter().all(|plane| plane.distance(center) >= -radius) {
+            objects.push(index);
+          }
+        }
+        ranges.push(offset);
+        ranges.push(objects.len() as u32 - offset);
+      }
+    }
+
+    Some(TileLists {tile_size: tile_size, ranges: ranges, objects: objects})
+  }
+
+  pub fn get_tile_size(&self) -> u32 {
+    self.tile_size
+  }
+
+  pub fn get_ranges(&self) -> &[u32] {
+    &self.ranges
+  }
+
+  pub fn get_objects(&self) -> &[u32] {
+    &self.objects
+  }
+}